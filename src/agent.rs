@@ -0,0 +1,223 @@
+/*
+ * An ssh-agent style helper that holds onto derived vault keys so that a run
+ * of commands doesn't need the password every time
+ * nothing talks to it unless it has been started and a vault unlocked, so
+ * the default is still giving the password for everything
+ */
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{env, fs, thread};
+
+use base64::{engine::general_purpose, Engine};
+use serde::{Serialize, Deserialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::secret::Secret;
+use crate::utils::config_dir;
+
+// keys go over the socket as base64 and get wiped once they're done with
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Request {
+    Get { vault: String },
+    Add { vault: String, key: String },
+    Lock { vault: Option<String> },
+    Stop,
+}
+
+impl Drop for Request {
+    fn drop(&mut self) {
+        if let Request::Add { key, .. } = self {
+            key.zeroize();
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Response {
+    Key(String),
+    Missing,
+    Done,
+}
+
+impl Drop for Response {
+    fn drop(&mut self) {
+        if let Response::Key(key) = self {
+            key.zeroize();
+        }
+    }
+}
+
+// the key is kept in locked memory and wiped once the agent lets go of it
+struct HeldKey {
+    key: Secret<Vec<u8>>,
+    used: Instant,
+}
+
+pub fn socket_path() -> PathBuf {
+    match env::var("TURT_AGENT_SOCK") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => {
+            let mut path = config_dir();
+            path.push("agent.sock");
+            path
+        }
+    }
+}
+
+pub struct Agent {
+    pub path: PathBuf,
+}
+
+impl Default for Agent {
+    fn default() -> Self {
+        Agent::at(socket_path())
+    }
+}
+
+impl Agent {
+    pub fn at(path: PathBuf) -> Self {
+        Agent { path }
+    }
+
+    pub fn running(&self) -> bool {
+        UnixStream::connect(&self.path).is_ok()
+    }
+
+    fn request(&self, request: &Request) -> Result<Response, anyhow::Error> {
+        let mut stream = UnixStream::connect(&self.path)?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut line = Zeroizing::new(serde_json::to_string(request)?);
+        line.push('\n');
+        stream.write_all(line.as_bytes())?;
+
+        let mut reply = Zeroizing::new(String::new());
+        BufReader::new(stream).read_line(&mut reply)?;
+        Ok(serde_json::from_str(&reply)?)
+    }
+
+    // the key for a vault if it's been unlocked, any trouble reaching the
+    // agent is the same as it not having the key
+    pub fn get(&self, vault: &str) -> Option<Secret<Vec<u8>>> {
+        match &self.request(&Request::Get { vault: vault.to_string() }).ok()? {
+            Response::Key(key) => general_purpose::STANDARD.decode(key).ok().map(Secret::new),
+            _ => None,
+        }
+    }
+
+    pub fn add(&self, vault: &str, key: &[u8]) -> Result<(), anyhow::Error> {
+        self.request(&Request::Add { vault: vault.to_string(), key: general_purpose::STANDARD.encode(key) })?;
+        Ok(())
+    }
+
+    // forget the key for a vault, or every key
+    pub fn lock(&self, vault: Option<&str>) -> Result<(), anyhow::Error> {
+        self.request(&Request::Lock { vault: vault.map(|v| v.to_string()) })?;
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<(), anyhow::Error> {
+        self.request(&Request::Stop)?;
+        Ok(())
+    }
+
+    // run the agent until it is told to stop, keys are forgotten once they
+    // haven't been used for the timeout
+    pub fn serve(&self, timeout: Duration) -> Result<(), anyhow::Error> {
+        if self.path.exists() {
+            if self.running() {
+                anyhow::bail!("An agent is already running at {:?}", self.path);
+            }
+            fs::remove_file(&self.path)?;
+        }
+
+        // the socket never exists with anything but owner permissions
+        let umask = unsafe { libc::umask(0o177) };
+        let listener = UnixListener::bind(&self.path);
+        unsafe { libc::umask(umask) };
+        let listener = listener?;
+        fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))?;
+        // no core dumps or ptrace from other processes with the keys in memory
+        #[cfg(target_os = "linux")]
+        unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0) };
+
+        let keys: Arc<Mutex<HashMap<String, HeldKey>>> = Arc::new(Mutex::new(HashMap::new()));
+        let expiring = keys.clone();
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(250));
+            expiring.lock().unwrap().retain(|_, held| held.used.elapsed() < timeout);
+        });
+
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) if same_user(&stream) => stream,
+                _ => continue,
+            };
+            if let Ok(true) = handle(stream, &keys) {
+                break;
+            }
+        }
+        keys.lock().unwrap().clear();
+        fs::remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+// answer one request, true when the agent should stop
+fn handle(stream: UnixStream, keys: &Mutex<HashMap<String, HeldKey>>) -> Result<bool, anyhow::Error> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut line = Zeroizing::new(String::new());
+    BufReader::new(&stream).read_line(&mut line)?;
+    let request: Request = serde_json::from_str(&line)?;
+
+    let mut keys = keys.lock().unwrap();
+    let response = match &request {
+        Request::Get { vault } => match keys.get_mut(vault) {
+            Some(held) => {
+                held.used = Instant::now();
+                Response::Key(general_purpose::STANDARD.encode(&*held.key))
+            }
+            None => Response::Missing,
+        },
+        Request::Add { vault, key } => {
+            let key = Secret::new(general_purpose::STANDARD.decode(key)?);
+            keys.insert(vault.clone(), HeldKey { key, used: Instant::now() });
+            Response::Done
+        }
+        Request::Lock { vault: Some(vault) } => {
+            keys.remove(vault);
+            Response::Done
+        }
+        Request::Lock { vault: None } | Request::Stop => {
+            keys.clear();
+            Response::Done
+        }
+    };
+
+    let mut reply = Zeroizing::new(serde_json::to_string(&response)?);
+    reply.push('\n');
+    (&stream).write_all(reply.as_bytes())?;
+    Ok(matches!(request, Request::Stop))
+}
+
+// the socket permissions should already keep others out, but check anyway
+#[cfg(target_os = "linux")]
+fn same_user(stream: &UnixStream) -> bool {
+    use std::os::unix::io::AsRawFd;
+    let mut cred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(stream.as_raw_fd(), libc::SOL_SOCKET, libc::SO_PEERCRED, &mut cred as *mut libc::ucred as *mut libc::c_void, &mut len)
+    };
+    res == 0 && cred.uid == unsafe { libc::getuid() }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn same_user(_stream: &UnixStream) -> bool {
+    true
+}
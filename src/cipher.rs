@@ -0,0 +1,96 @@
+/*
+ * What the vault data gets encrypted with
+ * Fernet is what vaults started out with, new ones use XChaCha20-Poly1305 so
+ * the key is 256 bits and the vault header gets authenticated with the data
+ */
+
+use base64::{engine::general_purpose, Engine};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use fernet::Fernet;
+use rand::rngs::OsRng;
+use serde::{Serialize, Deserialize};
+
+use crate::secret::Secret;
+
+// Fernet is what vaults from before the header were encrypted with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Cipher {
+    Fernet,
+    XChaCha20Poly1305,
+}
+
+// XChaCha20Poly1305 wipes its own key, fernet doesn't so only the raw key is
+// kept and a Fernet gets made for as long as it's needed
+pub enum Key {
+    Fernet(Secret<Vec<u8>>),
+    XChaCha20Poly1305(XChaCha20Poly1305),
+}
+
+fn fernet(key: &[u8]) -> Result<Fernet, anyhow::Error> {
+    let encoded = Secret::new(general_purpose::URL_SAFE.encode(key));
+    match Fernet::new(&encoded) {
+        Some(fernet) => Ok(fernet),
+        None => anyhow::bail!("Invalid key"),
+    }
+}
+
+impl Key {
+    pub fn new(cipher: Cipher, key: &[u8]) -> Option<Self> {
+        match cipher {
+            Cipher::Fernet => fernet(key).ok().map(|_| Key::Fernet(Secret::new(key.to_vec()))),
+            Cipher::XChaCha20Poly1305 => XChaCha20Poly1305::new_from_slice(key).ok().map(Key::XChaCha20Poly1305),
+        }
+    }
+
+    // the associated data is authenticated but not encrypted, Fernet has no
+    // way to do that so it gets ignored there
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+        match self {
+            Key::Fernet(key) => Ok(fernet(key)?.encrypt(plaintext).into_bytes()),
+            Key::XChaCha20Poly1305(cipher) => {
+                let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+                let encrypted = match cipher.encrypt(&nonce, Payload { msg: plaintext, aad }) {
+                    Ok(encrypted) => encrypted,
+                    Err(_) => anyhow::bail!("Failed to encrypt"),
+                };
+                let mut out = nonce.to_vec();
+                out.extend(encrypted);
+                Ok(out)
+            }
+        }
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+        match self {
+            Key::Fernet(key) => Ok(fernet(key)?.decrypt(std::str::from_utf8(ciphertext)?)?),
+            Key::XChaCha20Poly1305(cipher) => {
+                if ciphertext.len() < 24 {
+                    anyhow::bail!("Encrypted data is cut short");
+                }
+                let (nonce, msg) = ciphertext.split_at(24);
+                match cipher.decrypt(XNonce::from_slice(nonce), Payload { msg, aad }) {
+                    Ok(decrypted) => Ok(decrypted),
+                    Err(_) => anyhow::bail!("Failed to decrypt, either the password is wrong or the vault has been tampered with"),
+                }
+            }
+        }
+    }
+}
+
+// a key encrypted under another one, for keeping the data key in the header
+pub fn wrap_key(wrapping: &[u8], key: &[u8]) -> Result<String, anyhow::Error> {
+    let wrapping = match Key::new(Cipher::XChaCha20Poly1305, wrapping) {
+        Some(wrapping) => wrapping,
+        None => anyhow::bail!("Invalid key"),
+    };
+    Ok(general_purpose::STANDARD.encode(wrapping.encrypt(key, &[])?))
+}
+
+pub fn unwrap_key(wrapping: &[u8], wrapped: &str) -> Result<Secret<Vec<u8>>, anyhow::Error> {
+    let wrapping = match Key::new(Cipher::XChaCha20Poly1305, wrapping) {
+        Some(wrapping) => wrapping,
+        None => anyhow::bail!("Invalid key"),
+    };
+    Ok(Secret::new(wrapping.decrypt(&general_purpose::STANDARD.decode(wrapped)?, &[])?))
+}
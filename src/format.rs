@@ -0,0 +1,218 @@
+/*
+ * The file a vault is kept in
+ * magic bytes, the format version, a header saying how to get the key and
+ * what the data is encrypted with, and then the encrypted data itself
+ *
+ * TURT | version (u16) | header length (u32) | header (json) | ciphertext
+ *
+ * everything before the ciphertext is the prefix, which ciphers that can
+ * authenticate extra data get given so the header can't be swapped out
+ */
+
+use std::path::PathBuf;
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use scrypt::password_hash::SaltString;
+use serde::{Serialize, Deserialize};
+
+use crate::cipher::{unwrap_key, wrap_key, Cipher, Key};
+use crate::kdf::Kdf;
+use crate::keyfile;
+use crate::member::Member;
+use crate::secret::Secret;
+
+pub const MAGIC: &[u8; 4] = b"TURT";
+pub const VERSION: u16 = 1;
+// how many old copies of a vault are kept unless it says otherwise
+pub const BACKUPS: u32 = 10;
+
+// for vault and data keys
+pub fn random_key() -> Secret<Vec<u8>> {
+    let mut bytes = vec![0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    Secret::new(bytes)
+}
+
+// one of the passwords that opens the vault, each gets its own salt and wraps
+// the same vault key
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Slot {
+    pub name: String,
+    pub kdf: Kdf,
+    salt: String,
+    // the vault key under the key from this slot's password, vaults from
+    // before there were slots use the key from the password as the vault key
+    wrapped: Option<String>,
+}
+
+impl Slot {
+    // the raw key from the password
+    fn derive(&self, password: &Secret<String>, keyfile: Option<&[u8]>) -> Result<Secret<Vec<u8>>, anyhow::Error> {
+        let derived = match keyfile {
+            Some(keyfile) => self.kdf.derive(&keyfile::combine(password, keyfile), &self.salt),
+            None => self.kdf.derive(password.as_bytes(), &self.salt),
+        };
+        match derived {
+            Some(secret) => Ok(secret),
+            None => anyhow::bail!("Failed to derive the key"),
+        }
+    }
+
+    pub fn is_wrapped(&self) -> bool {
+        self.wrapped.is_some()
+    }
+}
+
+// what is needed to get the key back from a password or a member's identity
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Header {
+    pub slots: Vec<Slot>,
+    pub cipher: Cipher,
+    pub backups: u32,
+    // whether a keyfile is needed as well as the password
+    pub uses_keyfile: bool,
+    // where the keyfile was when the vault was made, only if asked to remember
+    // since the header isn't encrypted
+    pub keyfile: Option<PathBuf>,
+    // the data key wrapped with the vault key, vaults from before there was a
+    // data key use the vault key directly
+    pub wrapped: Option<String>,
+    pub members: Vec<Member>,
+}
+
+impl Header {
+    // nothing opens it until it has a slot and has been sealed
+    pub fn generate() -> Self {
+        Header { slots: Vec::new(), cipher: Cipher::XChaCha20Poly1305, backups: BACKUPS, uses_keyfile: false, keyfile: None, wrapped: None, members: Vec::new() }
+    }
+
+    // a header for vaults from before there was a header, with the password
+    // used directly with the old parameters
+    pub fn legacy(salt: String) -> Self {
+        let slot = Slot { name: "default".to_string(), kdf: Kdf::legacy(), salt, wrapped: None };
+        Header { slots: vec![slot], cipher: Cipher::Fernet, ..Header::generate() }
+    }
+
+    // the keyfile is the hash of its contents, as given by `keyfile::read`
+    // every slot gets tried, so this is as slow as the password is far down
+    pub fn unlock(&self, password: &Secret<String>, keyfile: Option<&[u8]>) -> Result<(String, Secret<Vec<u8>>), anyhow::Error> {
+        if self.uses_keyfile && keyfile.is_none() {
+            anyhow::bail!("The vault needs its keyfile as well as the password");
+        }
+        let keyfile = keyfile.filter(|_| self.uses_keyfile);
+        for slot in &self.slots {
+            let derived = slot.derive(password, keyfile)?;
+            match &slot.wrapped {
+                Some(wrapped) => if let Ok(unlock) = unwrap_key(&derived, wrapped) {
+                    return Ok((slot.name.clone(), unlock));
+                },
+                // nothing to check it against, reading the data will
+                None => return Ok((slot.name.clone(), derived)),
+            }
+        }
+        anyhow::bail!("Failed to decrypt, either the password is wrong or the vault has been tampered with")
+    }
+
+    // a new slot wrapping the vault key, or a new password for an existing
+    // one, which keeps its place
+    pub fn set_slot(&mut self, name: &str, kdf: Kdf, password: &Secret<String>, keyfile: Option<&[u8]>, unlock: &[u8]) -> Result<(), anyhow::Error> {
+        if self.uses_keyfile && keyfile.is_none() {
+            anyhow::bail!("The vault needs its keyfile as well as the password");
+        }
+        let mut slot = Slot { name: name.to_string(), kdf, salt: SaltString::generate(&mut OsRng).to_string(), wrapped: None };
+        let derived = slot.derive(password, keyfile.filter(|_| self.uses_keyfile))?;
+        slot.wrapped = Some(wrap_key(&derived, unlock)?);
+        match self.slots.iter_mut().find(|s| s.name == name) {
+            Some(existing) => *existing = slot,
+            None => self.slots.push(slot),
+        }
+        Ok(())
+    }
+
+    // slots from before there was a vault key to wrap, the key from their
+    // password was the vault key
+    pub fn wrap_legacy(&mut self, old: &[u8], unlock: &[u8]) -> Result<(), anyhow::Error> {
+        for slot in self.slots.iter_mut().filter(|s| s.wrapped.is_none()) {
+            slot.wrapped = Some(wrap_key(old, unlock)?);
+        }
+        Ok(())
+    }
+
+    // a key from unlocking a slot or from the agent
+    pub fn key_from(&self, unlock: &[u8]) -> Result<Key, anyhow::Error> {
+        self.key_for(&self.data_key(unlock)?)
+    }
+
+    pub fn data_key(&self, unlock: &[u8]) -> Result<Secret<Vec<u8>>, anyhow::Error> {
+        match &self.wrapped {
+            Some(wrapped) => unwrap_key(unlock, wrapped),
+            None => Ok(Secret::new(unlock.to_vec())),
+        }
+    }
+
+    // a new random data key, wrapped with the vault key and for every member
+    pub fn seal(&mut self, unlock: &[u8]) -> Result<Secret<Vec<u8>>, anyhow::Error> {
+        let data_key = random_key();
+        self.cipher = Cipher::XChaCha20Poly1305;
+        self.wrapped = Some(wrap_key(unlock, &data_key)?);
+        self.members = self.members.iter().map(|m| Member::wrap(&m.public, &data_key)).collect::<Result<_, _>>()?;
+        Ok(data_key)
+    }
+
+    pub fn key_for(&self, data_key: &[u8]) -> Result<Key, anyhow::Error> {
+        match Key::new(self.cipher, data_key) {
+            Some(key) => Ok(key),
+            None => anyhow::bail!("Failed to setup encryption"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Container {
+    pub version: u16,
+    pub header: Header,
+    prefix: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+impl Container {
+    // the ciphertext gets filled in afterwards since it needs the prefix
+    pub fn new(header: Header) -> Result<Self, anyhow::Error> {
+        let json = serde_json::to_vec(&header)?;
+        let mut prefix = Vec::with_capacity(10 + json.len());
+        prefix.extend_from_slice(MAGIC);
+        prefix.extend_from_slice(&VERSION.to_be_bytes());
+        prefix.extend_from_slice(&(json.len() as u32).to_be_bytes());
+        prefix.extend_from_slice(&json);
+        Ok(Container { version: VERSION, header, prefix, ciphertext: Vec::new() })
+    }
+
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.prefix.clone();
+        out.extend_from_slice(&self.ciphertext);
+        out
+    }
+
+    // the prefix is kept exactly as it was read, re-serializing the header
+    // might not give back the same bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        if bytes.len() < 10 || &bytes[..4] != MAGIC {
+            anyhow::bail!("Not a turt vault");
+        }
+        let version = u16::from_be_bytes([bytes[4], bytes[5]]);
+        if version > VERSION {
+            anyhow::bail!("Vault is format version {} but only up to {} is supported, a newer turt is needed", version, VERSION);
+        }
+        let len = u32::from_be_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]) as usize;
+        let header = match bytes.get(10..10 + len) {
+            Some(header) => serde_json::from_slice(header)?,
+            None => anyhow::bail!("Vault header is cut short"),
+        };
+        Ok(Container { version, header, prefix: bytes[..10 + len].to_vec(), ciphertext: bytes[10 + len..].to_vec() })
+    }
+}
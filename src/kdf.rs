@@ -0,0 +1,88 @@
+/*
+ * Turning the vault password into a key
+ * the parameters get stored with the vault so the work factor can go up over
+ * time without older vaults becoming unreadable
+ */
+
+use std::fmt::Display;
+
+use argon2::Argon2;
+use serde::{Serialize, Deserialize};
+
+use crate::secret::Secret;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Kdf {
+    Scrypt { log_n: u8, r: u32, p: u32 },
+    // memory is in KiB
+    Argon2id { memory: u32, iterations: u32, parallelism: u32 },
+}
+
+impl Kdf {
+    // what vaults used before the parameters were stored
+    pub fn legacy() -> Self {
+        Kdf::Scrypt { log_n: 16, r: 8, p: 1 }
+    }
+
+    // the cost is the log2 of the work, for scrypt that is N and for argon2id
+    // the memory in KiB, so 16 means about 64MiB for either
+    pub fn new(name: &str, cost: u8) -> Option<Self> {
+        if !(10..=24).contains(&cost) {
+            return None;
+        }
+        match name.to_lowercase().as_str() {
+            "scrypt" => Some(Kdf::Scrypt { log_n: cost, r: 8, p: 1 }),
+            "argon2id" | "argon2" => Some(Kdf::Argon2id { memory: 1 << cost, iterations: 3, parallelism: 1 }),
+            _ => None,
+        }
+    }
+
+    // the name new takes for this algorithm
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kdf::Scrypt { .. } => "scrypt",
+            Kdf::Argon2id { .. } => "argon2id",
+        }
+    }
+
+    // the cost new would take to make this, rounded down for argon2id memory
+    // that isn't a power of two
+    pub fn cost(&self) -> u8 {
+        match *self {
+            Kdf::Scrypt { log_n, .. } => log_n,
+            Kdf::Argon2id { memory, .. } => memory.checked_ilog2().unwrap_or(0) as u8,
+        }
+    }
+
+    // the input is usually the password, or the password mixed with a keyfile
+    pub fn derive(&self, input: &[u8], salt: &str) -> Option<Secret<Vec<u8>>> {
+        let mut out = vec![0u8; 32];
+        match *self {
+            Kdf::Scrypt { log_n, r, p } => {
+                let params = scrypt::Params::new(log_n, r, p, 32).ok()?;
+                scrypt::scrypt(input, salt.as_bytes(), &params, &mut out).ok()?;
+            }
+            Kdf::Argon2id { memory, iterations, parallelism } => {
+                let params = argon2::Params::new(memory, iterations, parallelism, Some(32)).ok()?;
+                Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                    .hash_password_into(input, salt.as_bytes(), &mut out).ok()?;
+            }
+        }
+        Some(Secret::new(out))
+    }
+}
+
+impl Default for Kdf {
+    fn default() -> Self {
+        Kdf::legacy()
+    }
+}
+
+impl Display for Kdf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kdf::Scrypt { log_n, r, p } => write!(f, "scrypt (N=2^{}, r={}, p={})", log_n, r, p),
+            Kdf::Argon2id { memory, iterations, parallelism } => write!(f, "argon2id (m={}KiB, t={}, p={})", memory, iterations, parallelism),
+        }
+    }
+}
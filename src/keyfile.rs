@@ -0,0 +1,53 @@
+/*
+ * A second thing needed to open a vault besides its password
+ * any file works, but a fresh one full of random bytes on a usb stick is the
+ * idea, only a hash of the contents gets used so it can be any size
+ */
+
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::secret::Secret;
+
+// how many random bytes a generated keyfile gets
+pub const SIZE: usize = 64;
+
+// the hash of a keyfile's contents, which is what gets mixed into the key
+pub fn read(path: &Path) -> Result<Secret<Vec<u8>>, anyhow::Error> {
+    let contents = match fs::read(path) {
+        Ok(contents) => Secret::new(contents),
+        Err(e) => anyhow::bail!("Couldn't read the keyfile {:?}: {}", path, e),
+    };
+    if contents.is_empty() {
+        anyhow::bail!("The keyfile {:?} is empty", path);
+    }
+    Ok(Secret::new(Sha256::digest(&*contents).to_vec()))
+}
+
+// never overwrites anything, losing a keyfile means losing the vault
+pub fn generate(path: &Path) -> Result<(), anyhow::Error> {
+    let mut bytes = vec![0u8; SIZE];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    let contents = Secret::new(bytes);
+    let mut file = match fs::OpenOptions::new().write(true).create_new(true).mode(0o400).open(path) {
+        Ok(file) => file,
+        Err(e) => anyhow::bail!("Couldn't create the keyfile {:?}: {}", path, e),
+    };
+    file.write_all(&contents)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+// the key is derived from both hashed together, a vault without a keyfile
+// just uses the password as it is
+pub fn combine(password: &str, keyfile: &[u8]) -> Secret<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    hasher.update(Sha256::digest(password.as_bytes()));
+    hasher.update(keyfile);
+    Secret::new(hasher.finalize().to_vec())
+}
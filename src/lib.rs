@@ -0,0 +1,13 @@
+pub mod utils;
+pub mod agent;
+pub mod cipher;
+pub mod format;
+pub mod kdf;
+pub mod keyfile;
+pub mod member;
+pub mod otp;
+pub mod password;
+pub mod random;
+pub mod secret;
+pub mod strength;
+pub mod vault;
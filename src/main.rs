@@ -4,18 +4,23 @@
  * want to be able to handle those dumb contraints that are added on
  */
 
-pub mod password;
-pub mod vault;
-pub mod utils;
-
 use core::time;
-use std::{fs, thread};
+use std::{env, fs, process, thread};
+use std::io::Write;
+use std::path::PathBuf;
 use arboard::{Clipboard, SetExtLinux};
-use clap::{Parser, Subcommand};
-use password::{Password, generic, Choice};
-use anyhow;
-use utils::config_dir;
-use vault::Vault;
+use clap::{Args, Parser, Subcommand};
+use turt::agent::Agent;
+use turt::kdf::Kdf;
+use turt::keyfile;
+use turt::member::{identity_path, Identity};
+use turt::otp::{base32_decode, Algorithm, Method, Otp};
+use turt::password::{Password, Passphrase, Spec, generic, ambiguous, Choice};
+use turt::secret::Secret;
+use turt::strength::{estimate, Kind, Rating};
+use turt::utils::{config_dir, edit_secret};
+use turt::vault::{is_secret, Vault, VaultItem};
+use zeroize::Zeroizing;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about=None)]
@@ -29,12 +34,32 @@ enum Commands {
     // vault/entry interaction
     Get(GetCommand),
     Add(AddCommand),
+    Rotate(RotateCommand),
+    Set(SetCommand),
+    Unset(UnsetCommand),
+    Note(NoteCommand),
+    Otp(OtpCommand),
+    History(HistoryCommand),
+    Restore(RestoreCommand),
     Remove(RemoveCommand),
     // vault interaction
     Create(CreateCommand),
+    Passwd(PasswdCommand),
+    UpgradeKdf(UpgradeKdfCommand),
+    Backup(BackupCommand),
+    Keyfile(KeyfileCommand),
+    Keygen(KeygenCommand),
+    Member(MemberCommand),
+    Slot(SlotCommand),
+    Unlock(UnlockCommand),
+    Lock(LockCommand),
+    Agent(AgentCommand),
     Delete(DeleteCommand),
     // both
     List(ListCommand),
+    // no vault needed
+    Generate(GenerateCommand),
+    Strength(StrengthCommand),
 }
 
 #[derive(Debug, Parser)]
@@ -44,8 +69,12 @@ struct GetCommand {
     vault: String,
     #[arg(help="The entry to get the information for")]
     entry: String,
+    #[arg(short, long, default_value="password", help="The secret field to copy to the clipboard")]
+    field: String,
     #[arg(short, long, default_value_t=10, help="How long to hold the password in the clipboard for")]
     duration: u64,
+    #[arg(long, help="Print out any notes on the entry")]
+    show: bool,
 }
 
 #[derive(Debug, Parser)]
@@ -57,14 +86,210 @@ struct AddCommand {
     entry: String,
     #[arg(help="The username for the entry")]
     username: String,
-    #[arg(help="(Optional) Manually specified password if not generating the password")]
+    #[arg(conflicts_with="passphrase", help="(Optional) Manually specified password if not generating the password")]
     password: Option<String>,
+    #[command(flatten)]
+    spec: SpecArgs,
+    #[arg(long, help="store a manually specified password even if it is weak")]
+    force: bool,
+    #[arg(long, value_parser=parse_field, help="An extra field to store that gets printed by get, as key=value")]
+    field: Vec<(String, String)>,
+    #[arg(long, value_parser=parse_field, help="An extra secret field to store that is never printed, as key=value")]
+    secret_field: Vec<(String, String)>,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Set a field on an entry, secret fields are never printed and can be copied to the clipboard with get --field")]
+struct SetCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="The entry to set the field on")]
+    entry: String,
+    #[arg(help="The name of the field")]
+    field: String,
+    #[arg(help="(Optional) The value of the field, prompted for if not given")]
+    value: Option<String>,
+    #[arg(short, long, help="Mark the field as secret")]
+    secret: bool,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Write or edit a note on an entry with $EDITOR, the entry is created if it doesn't exist")]
+struct NoteCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="The entry to keep the note in")]
+    entry: String,
+    #[arg(short, long, default_value="note", help="The field to keep the note in")]
+    field: String,
+    #[arg(long, help="Edit in the temporary directory when there is no memory backed one, which can leave the note on the disk")]
+    allow_disk: bool,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Get the current one time password code for an entry, copied to clipboard. Use --setup to store the secret first")]
+struct OtpCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="The entry the one time password is for")]
+    entry: String,
+    #[arg(short, long, default_value="otp", help="The field to keep the one time password in")]
+    field: String,
+    #[arg(long, help="Store a new secret, prompted for as either an otpauth:// URI or a base32 secret")]
+    setup: bool,
+    #[arg(long, requires="setup", help="Use a counter based (HOTP) code for a base32 secret instead of a time based one")]
+    hotp: bool,
+    #[arg(long, default_value_t=0, help="The starting counter for HOTP")]
+    counter: u64,
+    #[arg(long, default_value="SHA1", help="The hash algorithm for a base32 secret (SHA1, SHA256 or SHA512)")]
+    algorithm: String,
+    #[arg(long, default_value_t=6, help="The number of digits in a code for a base32 secret")]
+    digits: u32,
+    #[arg(long, default_value_t=30, help="How many seconds a time based code lasts for a base32 secret")]
+    period: u64,
+    #[arg(short, long, default_value_t=10, help="How long to hold the code in the clipboard for")]
+    duration: u64,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Remove a field from an entry")]
+struct UnsetCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="The entry to remove the field from")]
+    entry: String,
+    #[arg(help="The name of the field")]
+    field: String,
+}
+
+fn parse_field(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected key=value, got '{}'", s)),
+    }
+}
+
+// the options for generating a password, shared between everything that
+// generates one
+#[derive(Debug, Args)]
+struct SpecArgs {
     #[arg(long, help="the allowed characters for the generated password, defaults to a reasonable group of ascii characters")]
     allowed: Option<String>,
     #[arg(long, default_value_t=32, help="length of the generated password")]
     length: u32,
-    #[arg(long, help="pattern for the generated password (a subset of 'digit+upper+lower+alpha+symbol')")]
+    #[arg(long, help="pattern for the generated password (a subset of 'digit+upper+lower+alpha+symbol', with optional counts like 'digit*3' or 'symbol{1,2}', and sets like '[!@#]' where one of the characters is required)")]
+    pattern: Option<String>,
+    #[arg(long, conflicts_with_all=["allowed", "pattern"], help="the site's password policy in the passwordrules format, e.g. 'required: upper; required: digit; maxlength: 20'")]
+    rules: Option<String>,
+    #[arg(long, help="characters that should never show up in the generated password")]
+    exclude: Option<String>,
+    #[arg(long, help="leave out characters that are easy to mix up like 0, O, 1, l and I")]
+    no_ambiguous: bool,
+    #[arg(long, help="the most times the same character can show up in a row in the generated password")]
+    max_consecutive: Option<u32>,
+    #[arg(long, help="don't allow runs like 'abc' or '321' in the generated password")]
+    no_sequences: bool,
+    #[arg(long, help="don't allow any character to show up twice in the generated password")]
+    no_repeats: bool,
+    #[arg(long, conflicts_with_all=["allowed", "pattern", "rules", "exclude", "no_ambiguous", "max_consecutive", "no_sequences", "no_repeats"], help="generate a passphrase of random words instead of a password")]
+    passphrase: bool,
+    #[arg(long, default_value_t=6, help="number of words in the generated passphrase")]
+    words: u32,
+    #[arg(long, default_value="-", help="separator between the words of the generated passphrase")]
+    separator: String,
+    #[arg(long, help="capitalize each word of the generated passphrase")]
+    capitalize: bool,
+    #[arg(long, help="add a random digit to the generated passphrase")]
+    with_digit: bool,
+    #[arg(long, help="add a random symbol to the generated passphrase")]
+    with_symbol: bool,
+}
+
+impl SpecArgs {
+    fn build(&self) -> Option<Spec> {
+        if self.passphrase {
+            let spec = Passphrase::new(self.words, self.separator.clone(), self.capitalize, self.with_digit, self.with_symbol)?;
+            return Some(spec.into());
+        }
+
+        let allowed = match &self.allowed {
+            Some(chars) => Choice::new(chars.chars().collect())?,
+            None => generic(),
+        };
+        let pattern = self.pattern.clone().unwrap_or("".to_string());
+        let mut spec = match &self.rules {
+            Some(rules) => Password::from_rules(rules, self.length)?,
+            None => Password::from_spec(allowed, self.length, pattern)?,
+        };
+
+        let mut exclude: Vec<char> = self.exclude.clone().unwrap_or("".to_string()).chars().collect();
+        if self.no_ambiguous {
+            exclude.extend(ambiguous().iter());
+        }
+        if let Some(exclude) = Choice::new(exclude) {
+            spec = spec.exclude(&exclude)?;
+        }
+        if let Some(max) = self.max_consecutive {
+            spec = spec.max_consecutive(max)?;
+        }
+        if self.no_sequences {
+            spec = spec.no_sequences();
+        }
+        if self.no_repeats {
+            spec = spec.no_repeats()?;
+        }
+        Some(spec.into())
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Generate passwords without storing them in a vault, printed out or copied to the clipboard")]
+struct GenerateCommand {
+    #[command(flatten)]
+    spec: SpecArgs,
+    #[arg(short, long, default_value_t=1, help="How many passwords to generate")]
+    count: u32,
+    #[arg(long, help="Copy the password to the clipboard instead of printing it")]
+    clip: bool,
+    #[arg(short, long, default_value_t=10, help="How long to hold the password in the clipboard for")]
+    duration: u64,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Generate a new password for an entry from the specification it was made with, the new password is copied to clipboard")]
+struct RotateCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="The entry to rotate the password of")]
+    entry: String,
+    #[arg(long, help="A new length for the password (the number of words for a passphrase)")]
+    length: Option<u32>,
+    #[arg(long, help="A new pattern for the password")]
     pattern: Option<String>,
+    #[arg(short, long, default_value_t=10, help="How long to hold the password in the clipboard for")]
+    duration: u64,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="List the previous values of an entry's fields, secrets are masked")]
+struct HistoryCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="The entry to get the history for")]
+    entry: String,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Bring back a previous value of an entry's field, the current value goes into the history")]
+struct RestoreCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="The entry to restore")]
+    entry: String,
+    #[arg(long, help="The version to restore, as numbered by the history command, leave it out to bring back a removed entry")]
+    version: Option<usize>,
+    #[arg(long, default_value="password", help="The field to restore")]
+    field: String,
 }
 
 #[derive(Debug, Parser)]
@@ -74,6 +299,8 @@ struct RemoveCommand {
     vault: String,
     #[arg(help="The entry to remove")]
     entry: String,
+    #[arg(long, help="Also remove the entry's history, works on already removed entries too")]
+    purge: bool,
 }
 
 #[derive(Debug, Parser)]
@@ -81,6 +308,211 @@ struct RemoveCommand {
 struct CreateCommand {
     #[arg(default_value="default", help="The vault to delete")]
     vault: String,
+    #[arg(long, default_value="scrypt", help="How to derive the key from the password, scrypt or argon2id")]
+    kdf: String,
+    #[arg(long, default_value_t=16, help="The log2 of the work for deriving the key, scrypt's N or argon2id's memory in KiB")]
+    kdf_cost: u8,
+    #[arg(long, help="A keyfile that is needed as well as the password to open the vault")]
+    keyfile: Option<PathBuf>,
+    #[arg(long, requires="keyfile", help="Keep where the keyfile is in the vault so TURT_KEYFILE isn't needed, anyone who can read the vault can see it")]
+    remember_keyfile: bool,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Move a vault to different key derivation parameters")]
+struct UpgradeKdfCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(long, help="How to derive the key from the password, scrypt or argon2id, defaults to what it uses now")]
+    kdf: Option<String>,
+    #[arg(long, help="The log2 of the work for deriving the key, scrypt's N or argon2id's memory in KiB")]
+    kdf_cost: u8,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Change the password for a vault, whichever slot it opens")]
+struct PasswdCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Work with the backups kept each time a vault changes")]
+struct BackupCommand {
+    #[command(subcommand)]
+    command: BackupCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum BackupCommands {
+    List(BackupListCommand),
+    Restore(BackupRestoreCommand),
+    Keep(BackupKeepCommand),
+}
+
+#[derive(Debug, Parser)]
+#[command(about="List the backups of a vault, 1 being the newest")]
+struct BackupListCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Put a vault back the way it was in a backup")]
+struct BackupRestoreCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="The backup to restore, as numbered by backup list")]
+    number: u32,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Set how many backups of a vault are kept")]
+struct BackupKeepCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="How many backups to keep, 0 turns them off")]
+    count: u32,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Make keyfiles for vaults that need one as well as the password, or stop a vault remembering where its keyfile is")]
+struct KeyfileCommand {
+    #[command(subcommand)]
+    command: KeyfileCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum KeyfileCommands {
+    Generate(KeyfileGenerateCommand),
+    Forget(KeyfileForgetCommand),
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Write a new keyfile full of random bytes, use it with turt create --keyfile")]
+struct KeyfileGenerateCommand {
+    #[arg(help="Where to put the keyfile, it won't replace an existing file")]
+    path: PathBuf,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Stop keeping where the keyfile is in the vault, it has to be given with TURT_KEYFILE after")]
+struct KeyfileForgetCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Make an identity so vaults can be shared with you, and show its public key")]
+struct KeygenCommand {}
+
+#[derive(Debug, Parser)]
+#[command(about="Share a vault with other people's identities")]
+struct MemberCommand {
+    #[command(subcommand)]
+    command: MemberCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum MemberCommands {
+    Add(MemberAddCommand),
+    Remove(MemberRemoveCommand),
+    List(MemberListCommand),
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Let someone open the vault with their identity")]
+struct MemberAddCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="Their public key, from turt keygen")]
+    public: String,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Stop someone from opening the vault, this changes the vault's key")]
+struct MemberRemoveCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="Their public key")]
+    public: String,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="List who the vault is shared with")]
+struct MemberListCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Manage the passwords that open a vault, each has its own slot")]
+struct SlotCommand {
+    #[command(subcommand)]
+    command: SlotCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum SlotCommands {
+    Add(SlotAddCommand),
+    Remove(SlotRemoveCommand),
+    List(SlotListCommand),
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Add another password that opens the vault")]
+struct SlotAddCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="What to call the slot")]
+    name: String,
+    #[arg(long, help="How to derive the key from the password, scrypt or argon2id, defaults to what the slot it was opened with uses")]
+    kdf: Option<String>,
+    #[arg(long, help="The log2 of the work for deriving the key, scrypt's N or argon2id's memory in KiB, defaults to what the slot it was opened with uses")]
+    kdf_cost: Option<u8>,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Stop a slot's password from opening the vault")]
+struct SlotRemoveCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(help="The slot to remove")]
+    name: String,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="List the slots of a vault")]
+struct SlotListCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Give a vault's key to the agent so commands don't ask for the password")]
+struct UnlockCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Have the agent forget a vault's key")]
+struct LockCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(short, long, help="Forget the keys for every vault")]
+    all: bool,
+}
+
+#[derive(Debug, Parser)]
+#[command(about="Start an agent that holds onto unlocked vault keys")]
+struct AgentCommand {
+    #[arg(short, long, default_value_t=900, help="How many seconds a key can go unused before it is forgotten")]
+    timeout: u64,
+    #[arg(long, help="Stay in the foreground instead of running in the background")]
+    foreground: bool,
+    #[arg(long, help="Stop the running agent")]
+    stop: bool,
 }
 
 #[derive(Debug, Parser)]
@@ -97,6 +529,17 @@ struct ListCommand {
     vault: Option<String>,
 }
 
+#[derive(Debug, Parser)]
+#[command(about="Estimate how strong a password is, either one given directly or the one stored for an entry")]
+struct StrengthCommand {
+    #[arg(short, long, default_value="default", help="The vault to access")]
+    vault: String,
+    #[arg(short, long, help="The entry to check the password of")]
+    entry: Option<String>,
+    #[arg(conflicts_with="entry", help="(Optional) The password to check, prompted for if not given")]
+    password: Option<String>,
+}
+
 fn list_vaults() -> Result<Vec<String>, anyhow::Error> {
     let mut list = Vec::new();
     let path = config_dir();
@@ -111,9 +554,34 @@ fn list_vaults() -> Result<Vec<String>, anyhow::Error> {
     Ok(list)
 }
 
+// vault passwords are wiped once they are done with
+fn prompt_secret(prompt: &str) -> Secret<String> {
+    Secret::new(rpassword::prompt_password(prompt).expect("Prompting for password failed"))
+}
+
+// an unlocked agent or being a member of the vault saves asking for the
+// password
 fn new_vault(vault: String) -> Vault {
-    let password = rpassword::prompt_password("Vault password: ").expect("Prompting for password failed");
-    match Vault::new(vault.clone(), password) {
+    if let Some(v) = Agent::default().get(&vault).and_then(|key| Vault::with_key(vault.clone(), &key).ok()) {
+        return v;
+    }
+    if let Some(v) = Identity::load(&identity_path()).ok().and_then(|identity| Vault::with_identity(vault.clone(), &identity).ok()) {
+        return v;
+    }
+    prompt_vault(vault)
+}
+
+// where the keyfile is for vaults that need one, unless they remember where it
+// was when they were made
+fn keyfile_override() -> Option<PathBuf> {
+    env::var_os("TURT_KEYFILE").map(PathBuf::from)
+}
+
+// always asks for the password, for the things that shouldn't happen just
+// because the agent has the vault unlocked
+fn prompt_vault(vault: String) -> Vault {
+    let password = prompt_secret("Vault password: ");
+    match Vault::open(vault.clone(), password, keyfile_override().as_deref()) {
         Ok(v) => v,
         Err(e) => {
             panic!("Error decrypting vault {}: {:?}", vault, e);
@@ -121,6 +589,37 @@ fn new_vault(vault: String) -> Vault {
     }
 }
 
+// backups that couldn't be opened or rewritten don't get moved to the new key,
+// so whatever opened them before still does
+fn report_skipped(vault: &str, skipped: &[u32]) {
+    for number in skipped {
+        println!("Backup {} of {} couldn't be moved over to the new key and was left as it was, an old password may still open it", number, vault);
+    }
+}
+
+// hold onto the text in the clipboard for a while and then put back whatever
+// was there before
+fn copy_to_clipboard(text: Secret<String>, dur: u64) {
+    let mut threads = vec![];
+    let mut clip = Clipboard::new().unwrap();
+    let orig = clip.get_text().unwrap_or("".to_string());
+    threads.push(thread::spawn(move || {
+        println!("Copying to clipboard for {} seconds.", dur);
+        let _ = Clipboard::new().unwrap().set().wait().text(text.as_str());
+    }));
+    threads.push(thread::spawn(move || {
+        let wait_time = time::Duration::from_millis(dur*1000);
+        thread::sleep(wait_time);
+        let _ = Clipboard::new().unwrap().set_text(orig);
+        // lol, whatever
+        let wait_time = time::Duration::from_millis(1000);
+        thread::sleep(wait_time);
+    }));
+    for t in threads {
+        let _ = t.join();
+    }
+}
+
 fn main() {
     // just always make sure .turt exists
     let _ = fs::create_dir_all(config_dir());
@@ -129,10 +628,17 @@ fn main() {
 
     match &args.command {
         Commands::Create(data) => {
-            let password = rpassword::prompt_password("Vault password: ").expect("Prompting for password failed");
-            let confirm = rpassword::prompt_password("Confirm password: ").expect("Prompting for password failed");
-            if confirm == password {
-                match Vault::create(data.vault.clone(), password) {
+            let kdf = match Kdf::new(&data.kdf, data.kdf_cost) {
+                Some(kdf) => kdf,
+                None => {
+                    println!("Invalid key derivation, expected scrypt or argon2id with a cost from 10 to 24");
+                    return;
+                }
+            };
+            let password = prompt_secret("Vault password: ");
+            let confirm = prompt_secret("Confirm password: ");
+            if *confirm == *password {
+                match Vault::create_with(data.vault.clone(), password, kdf, data.keyfile.as_deref(), data.remember_keyfile) {
                     Ok(_) => {
                         println!("Created new vault");
                     }
@@ -144,77 +650,527 @@ fn main() {
                 println!("Password's do not match.");
             }
         }
+        Commands::Passwd(data) => {
+            let mut vault = prompt_vault(data.vault.clone());
+            let password = prompt_secret("New vault password: ");
+            let confirm = prompt_secret("Confirm new password: ");
+            if *confirm == *password {
+                match vault.change_password(password) {
+                    Ok(skipped) => {
+                        let _ = Agent::default().lock(Some(&data.vault));
+                        println!("Changed the password for {}", data.vault);
+                        report_skipped(&data.vault, &skipped);
+                    }
+                    Err(e) => {
+                        println!("Failed to change password: {:?}", e);
+                    }
+                }
+            } else {
+                println!("Password's do not match.");
+            }
+        }
+        Commands::UpgradeKdf(data) => {
+            // checked before the password is asked for, the algorithm it
+            // defaults to isn't known until the vault is open
+            if Kdf::new(data.kdf.as_deref().unwrap_or("scrypt"), data.kdf_cost).is_none() {
+                println!("Invalid key derivation, expected scrypt or argon2id with a cost from 10 to 24");
+                return;
+            }
+            // the password is needed again to derive the new key
+            let password = prompt_secret("Vault password: ");
+            let mut vault = match Vault::open(data.vault.clone(), Secret::from(password.as_str()), keyfile_override().as_deref()) {
+                Ok(v) => v,
+                Err(e) => panic!("Error decrypting vault {}: {:?}", data.vault, e),
+            };
+            let old = match vault.slot() {
+                Some(slot) => slot.kdf,
+                None => Kdf::default(),
+            };
+            let kdf = Kdf::new(data.kdf.as_deref().unwrap_or(old.name()), data.kdf_cost).unwrap();
+            match vault.upgrade_kdf(password, kdf) {
+                Ok(skipped) => {
+                    let _ = Agent::default().lock(Some(&data.vault));
+                    println!("Moved {} from {} to {}", data.vault, old, kdf);
+                    report_skipped(&data.vault, &skipped);
+                }
+                Err(e) => println!("Failed to change key derivation: {:?}", e),
+            }
+        }
+        Commands::Backup(backup) => match &backup.command {
+            BackupCommands::List(data) => {
+                let vault = new_vault(data.vault.clone());
+                match vault.backups() {
+                    Ok(backups) if !backups.is_empty() => {
+                        println!("Backups of {} (keeping {}):", data.vault, vault.header.backups);
+                        for backup in backups {
+                            let when = chrono::DateTime::from_timestamp(backup.saved as i64, 0)
+                                .map_or("unknown".to_string(), |d| d.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string());
+                            match backup.entries {
+                                Some(entries) => println!("  {}: {} ({} entries)", backup.number, when, entries),
+                                None => println!("  {}: {} (can't be opened)", backup.number, when),
+                            }
+                        }
+                    }
+                    Ok(_) => println!("No backups of {}", data.vault),
+                    Err(e) => println!("Failed to list backups: {:?}", e),
+                }
+            }
+            BackupCommands::Restore(data) => {
+                let mut vault = new_vault(data.vault.clone());
+                match vault.restore_backup(data.number) {
+                    Ok(_) => println!("Restored {} from backup {}, what it replaced is now backup 1", data.vault, data.number),
+                    Err(e) => println!("Failed to restore backup: {}", e),
+                }
+            }
+            BackupCommands::Keep(data) => {
+                let mut vault = new_vault(data.vault.clone());
+                match vault.set_backups(data.count) {
+                    Ok(_) => println!("Keeping {} backups of {}", data.count, data.vault),
+                    Err(e) => println!("Failed to update vault: {:?}", e),
+                }
+            }
+        },
+        Commands::Keyfile(keyfile) => match &keyfile.command {
+            KeyfileCommands::Generate(data) => match keyfile::generate(&data.path) {
+                Ok(_) => println!("Wrote a new keyfile to {:?}, without it the vaults using it can't be opened", data.path),
+                Err(e) => println!("Failed to make a keyfile: {}", e),
+            },
+            KeyfileCommands::Forget(data) => {
+                let mut vault = new_vault(data.vault.clone());
+                match vault.forget_keyfile() {
+                    Ok(_) => println!("{} no longer keeps where its keyfile is", data.vault),
+                    Err(e) => println!("Failed to forget the keyfile: {}", e),
+                }
+            }
+        },
+        Commands::Keygen(_) => {
+            let path = identity_path();
+            if let Ok(identity) = Identity::load(&path) {
+                println!("Already have an identity at {:?}, its public key is:", path);
+                println!("{}", identity.public());
+                return;
+            }
+            let identity = Identity::generate();
+            match identity.save(&path) {
+                Ok(_) => {
+                    println!("Saved a new identity to {:?}, give this public key to whoever is sharing a vault with you:", path);
+                    println!("{}", identity.public());
+                }
+                Err(e) => println!("Failed to save the identity: {}", e),
+            }
+        }
+        Commands::Member(member) => match &member.command {
+            MemberCommands::Add(data) => {
+                let mut vault = new_vault(data.vault.clone());
+                match vault.add_member(&data.public) {
+                    Ok(skipped) => {
+                        println!("Shared {} with {}", data.vault, data.public);
+                        report_skipped(&data.vault, &skipped);
+                    }
+                    Err(e) => println!("Failed to add member: {}", e),
+                }
+            }
+            MemberCommands::Remove(data) => {
+                // the vault gets a new key, which needs the password
+                let mut vault = prompt_vault(data.vault.clone());
+                match vault.remove_member(&data.public) {
+                    Ok(skipped) => {
+                        println!("Removed {} from {} and changed its key", data.public, data.vault);
+                        report_skipped(&data.vault, &skipped);
+                    }
+                    Err(e) => println!("Failed to remove member: {}", e),
+                }
+            }
+            MemberCommands::List(data) => {
+                let vault = new_vault(data.vault.clone());
+                if vault.header.members.is_empty() {
+                    println!("{} isn't shared with anyone", data.vault);
+                }
+                for member in &vault.header.members {
+                    println!("{}", member.public);
+                }
+            }
+        },
+        Commands::Slot(slot) => match &slot.command {
+            SlotCommands::Add(data) => {
+                // same as upgrade-kdf, the default algorithm and cost come
+                // from the vault
+                if Kdf::new(data.kdf.as_deref().unwrap_or("scrypt"), data.kdf_cost.unwrap_or(16)).is_none() {
+                    println!("Invalid key derivation, expected scrypt or argon2id with a cost from 10 to 24");
+                    return;
+                }
+                let mut vault = prompt_vault(data.vault.clone());
+                let current = vault.slot().map_or(Kdf::default(), |slot| slot.kdf);
+                let kdf = match Kdf::new(data.kdf.as_deref().unwrap_or(current.name()), data.kdf_cost.unwrap_or(current.cost())) {
+                    Some(kdf) => kdf,
+                    None => {
+                        println!("The current slot's key derivation can't be reused, give a --kdf-cost from 10 to 24");
+                        return;
+                    }
+                };
+                let password = prompt_secret("Password for the new slot: ");
+                let confirm = prompt_secret("Confirm password: ");
+                if *confirm != *password {
+                    println!("Password's do not match.");
+                    return;
+                }
+                match vault.add_slot(&data.name, password, kdf) {
+                    Ok(skipped) => {
+                        println!("Added slot {} to {}", data.name, data.vault);
+                        report_skipped(&data.vault, &skipped);
+                    }
+                    Err(e) => println!("Failed to add slot: {}", e),
+                }
+            }
+            SlotCommands::Remove(data) => {
+                let mut vault = prompt_vault(data.vault.clone());
+                match vault.remove_slot(&data.name) {
+                    Ok(skipped) => {
+                        let _ = Agent::default().lock(Some(&data.vault));
+                        println!("Removed slot {} from {}", data.name, data.vault);
+                        println!("Anyone who opened {} with that password could have kept its key, which still opens it, only moving the entries to a new vault shuts them out", data.vault);
+                        report_skipped(&data.vault, &skipped);
+                    }
+                    Err(e) => println!("Failed to remove slot: {}", e),
+                }
+            }
+            SlotCommands::List(data) => {
+                let vault = new_vault(data.vault.clone());
+                for slot in &vault.header.slots {
+                    println!("{}: {}", slot.name, slot.kdf);
+                }
+            }
+        },
+        Commands::Agent(data) => {
+            let agent = Agent::default();
+            if data.stop {
+                match agent.stop() {
+                    Ok(_) => println!("Stopped the agent"),
+                    Err(_) => println!("No agent running"),
+                }
+            } else if agent.running() {
+                println!("An agent is already running at {:?}", agent.path);
+            } else if data.foreground {
+                if let Err(e) = agent.serve(time::Duration::from_secs(data.timeout)) {
+                    println!("Agent failed: {:?}", e);
+                }
+            } else {
+                // run another turt as the agent so this one can return
+                let spawned = env::current_exe().and_then(|exe| {
+                    process::Command::new(exe)
+                        .args(["agent", "--foreground", "--timeout", &data.timeout.to_string()])
+                        .stdin(process::Stdio::null())
+                        .stdout(process::Stdio::null())
+                        .stderr(process::Stdio::null())
+                        .spawn()
+                });
+                if let Err(e) = spawned {
+                    println!("Failed to start the agent: {:?}", e);
+                    return;
+                }
+                for _ in 0..50 {
+                    if agent.running() {
+                        println!("Agent running at {:?}, use turt unlock to give it a vault", agent.path);
+                        return;
+                    }
+                    thread::sleep(time::Duration::from_millis(100));
+                }
+                println!("Agent didn't start");
+            }
+        }
+        Commands::Unlock(data) => {
+            let agent = Agent::default();
+            if !agent.running() {
+                println!("No agent running, start one with turt agent");
+                return;
+            }
+            let password = prompt_secret("Vault password: ");
+            let key = match Vault::derive_key(data.vault.clone(), password, keyfile_override().as_deref()) {
+                Ok(key) => key,
+                Err(e) => {
+                    println!("Failed to unlock {}: {}", data.vault, e);
+                    return;
+                }
+            };
+            // make sure the key actually opens the vault before handing it over
+            if let Err(e) = Vault::with_key(data.vault.clone(), &key) {
+                println!("Failed to unlock {}: {:?}", data.vault, e);
+                return;
+            }
+            match agent.add(&data.vault, &key) {
+                Ok(_) => println!("Unlocked {}", data.vault),
+                Err(e) => println!("Failed to give the key to the agent: {:?}", e),
+            }
+        }
+        Commands::Lock(data) => {
+            let vault = if data.all { None } else { Some(data.vault.as_str()) };
+            match Agent::default().lock(vault) {
+                Ok(_) if data.all => println!("Locked every vault"),
+                Ok(_) => println!("Locked {}", data.vault),
+                Err(_) => println!("No agent running"),
+            }
+        }
         Commands::Delete(data) => {
-            let vault = new_vault(data.vault.clone());
-            let _ = fs::remove_dir_all(vault.path.clone());
-            println!("Removed vault: {:?}", vault.path);
+            let vault = prompt_vault(data.vault.clone());
+            let _ = Agent::default().lock(Some(&data.vault));
+            let dir = vault.path.parent().unwrap_or(&vault.path);
+            let _ = fs::remove_dir_all(dir);
+            println!("Removed vault: {:?}", dir);
         }
         Commands::Get(data) => {
             let vault = new_vault(data.vault.clone());
-            if let Some(info) = vault.get(&data.entry) {
-                for (key, value) in info.iter() {
-                    if key != "password" {
-                        println!("{}: {}", key, value);
+            let copy = if let Some(info) = vault.get(&data.entry) {
+                let mut keys: Vec<&String> = info.keys().collect();
+                keys.sort();
+                for key in keys {
+                    let value = &info[key];
+                    match value {
+                        VaultItem::Note(note) if data.show => println!("{}:\n{}", key, note),
+                        VaultItem::Note(_) => println!("{}: (note, use --show to display it)", key),
+                        _ if !is_secret(key, value) => println!("{}: {}", key, value),
+                        _ if key != "password" => println!("{}: (secret)", key),
+                        _ => {}
                     }
                 }
-                if let Some(password) = info.get("password") {
-                    let dur = data.duration;
-                    let mut threads = vec![];
-                    let mut clip = Clipboard::new().unwrap();
-                    let orig = clip.get_text().unwrap_or("".to_string());
-                    let p = password.clone();
-                    threads.push(thread::spawn(move || {
-                        println!("Copying password to clipboard for {} seconds.", dur);
-                        let _ = Clipboard::new().unwrap().set().wait().text(p.to_string());
-                    }));
-                    threads.push(thread::spawn(move || {
-                        let wait_time = time::Duration::from_millis(dur*1000);
-                        thread::sleep(wait_time);
-                        let _ = Clipboard::new().unwrap().set_text(orig);
-                        // lol, whatever
-                        let wait_time = time::Duration::from_millis(1000);
-                        thread::sleep(wait_time);
-                    }));
-                    for t in threads {
-                        let _ = t.join();
+                match info.get(&data.field) {
+                    Some(value) if is_secret(&data.field, value) => Some(Secret::new(value.to_string())),
+                    Some(_) => {
+                        println!("{} isn't a secret field, so there is nothing to copy", data.field);
+                        None
+                    }
+                    None if data.field == "password" => None,
+                    None => {
+                        println!("No field {} for {}", data.field, data.entry);
+                        None
                     }
                 }
             } else {
                 println!("No entry for {}", data.entry);
+                None
+            };
+            // the vault is locked for as long as it is open, so let go of it
+            // rather than keeping everything else waiting on the clipboard
+            drop(vault);
+            if let Some(text) = copy {
+                copy_to_clipboard(text, data.duration);
             }
         }
         Commands::Add(data) => {
-            let mut vault = new_vault(data.vault.clone());
-            match &data.password {
+            // check before asking for the vault password so nothing is wasted
+            let password = match &data.password {
                 Some(p) => {
-                    if let Ok(_info) = vault.set(data.entry.clone(), data.username.clone(), p.clone()) {
-                        println!("Created new entry for {}", data.entry)
-                    } else {
-                        println!("Failed to create entry for {}", data.entry);
+                    let strength = estimate(p);
+                    println!("Password strength: {} (~{:.0} bits)", strength.rating(), strength.entropy);
+                    if strength.rating() < Rating::Reasonable && !data.force {
+                        println!("Refusing to store a {} password, use --force to store it anyways", strength.rating());
+                        return;
                     }
+                    VaultItem::Generic(p.clone())
                 }
-                None => {
-                    let length = data.length;
-                    let allowed = match &data.allowed {
-                        Some(chars) => Choice::new(chars.chars().collect()).unwrap(),
-                        None => generic(),
-                    };
-                    let pattern = data.pattern.clone().unwrap_or("".to_string());
-                    if let Some(spec) = Password::from_spec(allowed, length, pattern.clone()) {
-                        if let Ok(_info) = vault.set_password(data.entry.clone(), data.username.clone(), spec) {
-                            println!("Created new entry for {}", data.entry)
-                        } else {
-                            println!("Failed to create entry for {}", data.entry);
+                None => match data.spec.build() {
+                    Some(spec) => {
+                        println!("Password strength: {} (~{:.0} bits)", Rating::from_entropy(spec.entropy()), spec.entropy());
+                        match VaultItem::generated(spec) {
+                            Ok(item) => item,
+                            Err(e) => {
+                                println!("Failed to create entry for {}: {}", data.entry, e);
+                                return;
+                            }
                         }
-                    } else {
+                    }
+                    None => {
                         println!("Invalid password specification");
+                        return;
+                    }
+                },
+            };
+            let mut vault = new_vault(data.vault.clone());
+            let fields = data.field.iter().map(|(k, v)| (k.clone(), VaultItem::Generic(v.clone())))
+                .chain(data.secret_field.iter().map(|(k, v)| (k.clone(), VaultItem::Secret(v.clone()))))
+                .collect();
+            let res = vault.set_item(data.entry.clone(), data.username.clone(), password, fields);
+            match res {
+                Ok(_) => println!("Created new entry for {}", data.entry),
+                Err(e) => println!("Failed to create entry for {}: {}", data.entry, e),
+            }
+        }
+        Commands::Set(data) => {
+            let value = match &data.value {
+                Some(value) => Secret::new(value.clone()),
+                None if data.secret => prompt_secret(&format!("{}: ", data.field)),
+                None => {
+                    print!("{}: ", data.field);
+                    let _ = std::io::stdout().flush();
+                    let mut line = Zeroizing::new(String::new());
+                    std::io::stdin().read_line(&mut line).expect("Reading value failed");
+                    Secret::new(line.trim_end_matches(['\r', '\n']).to_string())
+                }
+            };
+            // the item wipes itself too, so it only gets its copy now
+            let item = if data.secret { VaultItem::Secret(value.to_string()) } else { VaultItem::Generic(value.to_string()) };
+            let mut vault = new_vault(data.vault.clone());
+            match vault.set_field(&data.entry, &data.field, item) {
+                Ok(_) => {
+                    println!("Set {} for {}", data.field, data.entry);
+                }
+                Err(e) => {
+                    println!("Failed to set {} for {}: {}", data.field, data.entry, e);
+                }
+            }
+        }
+        Commands::Note(data) => {
+            let mut vault = new_vault(data.vault.clone());
+            let current = match vault.get(&data.entry).and_then(|info| info.get(&data.field)) {
+                Some(VaultItem::Note(note)) => note.clone(),
+                Some(_) => {
+                    println!("{} on {} isn't a note", data.field, data.entry);
+                    return;
+                }
+                None => "".to_string(),
+            };
+            match edit_secret(&current, data.allow_disk) {
+                Ok(note) if note == current => {
+                    println!("No changes to {}", data.entry);
+                }
+                Ok(note) => {
+                    match vault.set_note(&data.entry, &data.field, note) {
+                        Ok(_) => println!("Saved note for {}", data.entry),
+                        Err(e) => println!("Failed to save note for {}: {}", data.entry, e),
                     }
                 }
+                Err(e) => {
+                    println!("Failed to edit note for {}: {}", data.entry, e);
+                }
+            }
+        }
+        Commands::Otp(data) if data.setup => {
+            let secret = prompt_secret("otpauth URI or base32 secret: ");
+            let otp = if secret.starts_with("otpauth://") {
+                Otp::from_uri(&secret)
+            } else {
+                let method = if data.hotp { Method::Hotp { counter: data.counter } } else { Method::Totp { period: data.period } };
+                Algorithm::parse(&data.algorithm)
+                    .zip(base32_decode(&secret))
+                    .and_then(|(algorithm, secret)| Otp::new(secret, algorithm, data.digits, method))
+            };
+            let otp = match otp {
+                Some(otp) => otp,
+                None => {
+                    println!("Invalid one time password secret");
+                    return;
+                }
+            };
+            let mut vault = new_vault(data.vault.clone());
+            match vault.set_otp(&data.entry, &data.field, otp) {
+                Ok(_) => println!("Saved one time password for {}", data.entry),
+                Err(e) => println!("Failed to save one time password for {}: {}", data.entry, e),
+            }
+        }
+        Commands::Otp(data) => {
+            let mut vault = new_vault(data.vault.clone());
+            let res = vault.otp(&data.entry, &data.field);
+            // not held while the clipboard waits, same as get
+            drop(vault);
+            match res {
+                Ok((code, remaining)) => {
+                    if let Some(remaining) = remaining {
+                        println!("Code is good for {} more seconds", remaining);
+                    }
+                    copy_to_clipboard(Secret::new(code), data.duration);
+                }
+                Err(e) => {
+                    println!("Failed to get a code for {}: {}", data.entry, e);
+                }
+            }
+        }
+        Commands::Unset(data) => {
+            let mut vault = new_vault(data.vault.clone());
+            match vault.unset_field(&data.entry, &data.field) {
+                Ok(_) => {
+                    println!("Removed {} from {}", data.field, data.entry);
+                }
+                Err(e) => {
+                    println!("Failed to remove {} from {}: {}", data.field, data.entry, e);
+                }
+            }
+        }
+        Commands::Rotate(data) => {
+            let mut vault = new_vault(data.vault.clone());
+            let res = vault.rotate(&data.entry, data.length, data.pattern.clone());
+            // not held while the clipboard waits, same as get
+            drop(vault);
+            match res {
+                Ok(password) => {
+                    println!("Rotated password for {}", data.entry);
+                    copy_to_clipboard(Secret::new(password), data.duration);
+                }
+                Err(e) => {
+                    println!("Failed to rotate password for {}: {}", data.entry, e);
+                }
+            }
+        }
+        Commands::History(data) => {
+            let vault = new_vault(data.vault.clone());
+            match vault.history(&data.entry) {
+                Some(history) if !history.values().all(|items| items.is_empty()) => {
+                    println!("History for {}:", data.entry);
+                    let mut fields: Vec<&String> = history.keys().collect();
+                    fields.sort();
+                    for field in fields {
+                        let items = &history[field];
+                        if items.is_empty() {
+                            continue;
+                        }
+                        println!(" {}:", field);
+                        // newest first, matching the numbers restore uses
+                        for (i, old) in items.iter().rev().enumerate() {
+                            // always the same width so not even the length shows
+                            let value = if is_secret(field, &old.item) {
+                                "********".to_string()
+                            } else {
+                                old.item.to_string()
+                            };
+                            let when = chrono::DateTime::from_timestamp(old.replaced as i64, 0)
+                                .map_or("unknown".to_string(), |d| d.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string());
+                            println!("  {}: {} (replaced {})", i + 1, value, when);
+                        }
+                    }
+                }
+                _ => {
+                    println!("No history for {}", data.entry);
+                }
+            }
+        }
+        Commands::Restore(data) => {
+            let mut vault = new_vault(data.vault.clone());
+            let res = match data.version {
+                Some(version) => vault.restore(&data.entry, &data.field, version)
+                    .map(|_| format!("Restored version {} of {} for {}", version, data.field, data.entry)),
+                None => vault.restore_entry(&data.entry)
+                    .map(|_| format!("Restored removed entry {}", data.entry)),
+            };
+            match res {
+                Ok(message) => {
+                    println!("{}", message);
+                }
+                Err(e) => {
+                    println!("Failed to restore {}: {}", data.entry, e);
+                }
             }
         }
         Commands::Remove(data) => {
             let mut vault = new_vault(data.vault.clone());
-            if let Ok(_info) = vault.remove(&data.entry) {
+            if data.purge {
+                match vault.purge(&data.entry) {
+                    Ok(skipped) => {
+                        println!("Removed entry {} and its history, from the backups too", data.entry);
+                        for number in skipped {
+                            println!("Backup {} couldn't be changed and may still have {} in it", number, data.entry);
+                        }
+                    }
+                    Err(e) => println!("Failed to purge {}: {}", data.entry, e),
+                }
+            } else if let Ok(_info) = vault.remove(&data.entry) {
                 println!("Removed entry {}", data.entry)
             } else {
                 println!("Failed to remove entry {}", data.entry);
@@ -225,7 +1181,7 @@ fn main() {
                 Some(name) => {
                     let vault = new_vault(name.to_string());
                     let entries = vault.entries();
-                    if entries.len() == 0 {
+                    if entries.is_empty() {
                         println!("No entries for {} yet", vault.id);
                     } else {
                         println!("Entries in {}:", vault.id);
@@ -237,7 +1193,7 @@ fn main() {
                 None => {
                     match list_vaults() {
                         Ok(list) => {
-                            if list.len() == 0 {
+                            if list.is_empty() {
                                 println!("No vaults defined yet");
                             } else {
                                 println!("Existing vaults:");
@@ -253,5 +1209,62 @@ fn main() {
                 }
             }
         }
+        Commands::Strength(data) => {
+            let password = match &data.entry {
+                Some(entry) => {
+                    let vault = new_vault(data.vault.clone());
+                    match vault.get(entry).and_then(|info| info.get("password")) {
+                        Some(item) => {
+                            if let Some(spec) = item.spec() {
+                                println!("Generated with ~{:.0} bits of entropy", spec.entropy());
+                            }
+                            item.to_string()
+                        }
+                        None => {
+                            println!("No password for {}", entry);
+                            return;
+                        }
+                    }
+                }
+                None => match &data.password {
+                    Some(p) => p.clone(),
+                    None => rpassword::prompt_password("Password: ").expect("Prompting for password failed"),
+                },
+            };
+            let strength = estimate(&password);
+            println!("Strength: {} (~{:.0} bits)", strength.rating(), strength.entropy);
+            for m in strength.matches.iter().filter(|m| m.kind != Kind::Bruteforce) {
+                println!(" - {} ({} characters, ~{:.0} bits)", m.kind, m.token.chars().count(), m.entropy);
+            }
+        }
+        Commands::Generate(data) => {
+            let spec = match data.spec.build() {
+                Some(spec) => spec,
+                None => {
+                    println!("Invalid password specification");
+                    return;
+                }
+            };
+            if data.clip {
+                if data.count != 1 {
+                    println!("Can only copy a single password to the clipboard");
+                    return;
+                }
+                match spec.generate() {
+                    Some(password) => copy_to_clipboard(Secret::new(password), data.duration),
+                    None => println!("Couldn't generate a password that fits the specification"),
+                }
+            } else {
+                for _ in 0..data.count {
+                    match spec.generate() {
+                        Some(password) => println!("{}", password),
+                        None => {
+                            println!("Couldn't generate a password that fits the specification");
+                            return;
+                        }
+                    }
+                }
+            }
+        }
     }
 }
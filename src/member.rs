@@ -0,0 +1,132 @@
+/*
+ * Sharing a vault with other people
+ * the vault's data key gets wrapped for each member's x25519 public key the
+ * way age does it, so their identity opens the vault without the password
+ */
+
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::env;
+
+use base64::{engine::general_purpose, Engine};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use serde::{Serialize, Deserialize};
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::cipher::{unwrap_key, wrap_key};
+use crate::secret::Secret;
+use crate::utils::config_dir;
+
+const PUBLIC_PREFIX: &str = "turt-pub-";
+const SECRET_PREFIX: &str = "TURT-SECRET-";
+
+pub fn identity_path() -> PathBuf {
+    match env::var("TURT_IDENTITY") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => {
+            let mut path = config_dir();
+            path.push("identity");
+            path
+        }
+    }
+}
+
+pub fn parse_public(public: &str) -> Result<PublicKey, anyhow::Error> {
+    let bytes = public.strip_prefix(PUBLIC_PREFIX)
+        .and_then(|b| general_purpose::URL_SAFE_NO_PAD.decode(b).ok())
+        .and_then(|b| <[u8; 32]>::try_from(b).ok());
+    match bytes {
+        Some(bytes) => Ok(PublicKey::from(bytes)),
+        None => anyhow::bail!("Not a turt public key, expected something like {}...", PUBLIC_PREFIX),
+    }
+}
+
+fn encode_public(public: &PublicKey) -> String {
+    format!("{}{}", PUBLIC_PREFIX, general_purpose::URL_SAFE_NO_PAD.encode(public.as_bytes()))
+}
+
+// the key the data key gets wrapped with, tied to both public keys
+fn wrapping_key(shared: &[u8], ephemeral: &PublicKey, public: &PublicKey) -> Secret<Vec<u8>> {
+    let mut salt = ephemeral.as_bytes().to_vec();
+    salt.extend_from_slice(public.as_bytes());
+    let mut out = vec![0u8; 32];
+    // 32 bytes is always a valid length for sha256
+    Hkdf::<Sha256>::new(Some(&salt), shared).expand(b"turt member", &mut out).unwrap();
+    Secret::new(out)
+}
+
+// the secret half, StaticSecret wipes itself when dropped
+pub struct Identity {
+    secret: StaticSecret,
+}
+
+impl Identity {
+    pub fn generate() -> Self {
+        Identity { secret: StaticSecret::random_from_rng(OsRng) }
+    }
+
+    pub fn public(&self) -> String {
+        encode_public(&PublicKey::from(&self.secret))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let contents = Secret::new(fs::read_to_string(path)?);
+        let bytes = contents.trim().strip_prefix(SECRET_PREFIX)
+            .and_then(|b| general_purpose::URL_SAFE_NO_PAD.decode(b).ok())
+            .map(Secret::new);
+        match bytes.as_ref().and_then(|b| <[u8; 32]>::try_from(&b[..]).ok()) {
+            Some(bytes) => Ok(Identity { secret: StaticSecret::from(bytes) }),
+            None => anyhow::bail!("{:?} isn't a turt identity", path),
+        }
+    }
+
+    // never replaces an identity, that would lock it out of its vaults
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let encoded = Secret::new(format!("{}{}\n", SECRET_PREFIX, general_purpose::URL_SAFE_NO_PAD.encode(self.secret.as_bytes())));
+        let mut file = fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)?;
+        file.write_all(encoded.as_bytes())?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    // the data key from whichever member is this identity
+    pub fn unwrap(&self, members: &[Member]) -> Result<Secret<Vec<u8>>, anyhow::Error> {
+        let public = self.public();
+        let member = match members.iter().find(|m| m.public == public) {
+            Some(member) => member,
+            None => anyhow::bail!("{} isn't a member of the vault", public),
+        };
+        let ephemeral = parse_public(&member.ephemeral)?;
+        let shared = self.secret.diffie_hellman(&ephemeral);
+        let wrapping = wrapping_key(shared.as_bytes(), &ephemeral, &PublicKey::from(&self.secret));
+        unwrap_key(&wrapping, &member.wrapped)
+    }
+}
+
+// a member as kept in the vault header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Member {
+    pub public: String,
+    ephemeral: String,
+    wrapped: String,
+}
+
+impl Member {
+    // a new ephemeral key every time, so nothing but the member's identity
+    // can get the data key back out
+    pub fn wrap(public: &str, data_key: &[u8]) -> Result<Self, anyhow::Error> {
+        let recipient = parse_public(public)?;
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let ephemeral = PublicKey::from(&secret);
+        let shared = secret.diffie_hellman(&recipient);
+        if !shared.was_contributory() {
+            anyhow::bail!("{} isn't a usable public key", public);
+        }
+        let wrapping = wrapping_key(shared.as_bytes(), &ephemeral, &recipient);
+        Ok(Member { public: encode_public(&recipient), ephemeral: encode_public(&ephemeral), wrapped: wrap_key(&wrapping, data_key)? })
+    }
+}
@@ -1,3 +0,0 @@
-pub mod utils;
-pub mod password;
-pub mod vault;
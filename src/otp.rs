@@ -0,0 +1,262 @@
+/*
+ * One time passwords for two factor logins
+ * TOTP (RFC 6238) is what pretty much everything uses, HOTP (RFC 4226) is
+ * the counter based version that TOTP is built on
+ */
+
+use std::fmt::Display;
+
+use hmac::{Hmac, Mac};
+use hmac::digest::KeyInit;
+use serde::{Serialize, Deserialize};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use zeroize::Zeroize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "SHA1" => Some(Algorithm::Sha1),
+            "SHA256" => Some(Algorithm::Sha256),
+            "SHA512" => Some(Algorithm::Sha512),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Algorithm::Sha1 => "SHA1".fmt(f),
+            Algorithm::Sha256 => "SHA256".fmt(f),
+            Algorithm::Sha512 => "SHA512".fmt(f),
+        }
+    }
+}
+
+// time based codes change every period, counter based ones every time a code
+// is used so the counter has to be saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Method {
+    Totp { period: u64 },
+    Hotp { counter: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Otp {
+    secret: Vec<u8>,
+    algorithm: Algorithm,
+    digits: u32,
+    method: Method,
+    label: Option<String>,
+    issuer: Option<String>,
+}
+
+impl Drop for Otp {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl Otp {
+    pub fn new(secret: Vec<u8>, algorithm: Algorithm, digits: u32, method: Method) -> Option<Self> {
+        if secret.is_empty() || !(6..=10).contains(&digits) {
+            return None;
+        }
+        if let Method::Totp { period: 0 } = method {
+            return None;
+        }
+        Some(Otp { secret, algorithm, digits, method, label: None, issuer: None })
+    }
+
+    // the usual defaults, SHA1 with 6 digits every 30 seconds
+    pub fn totp(secret: &str) -> Option<Self> {
+        Otp::new(base32_decode(secret)?, Algorithm::Sha1, 6, Method::Totp { period: 30 })
+    }
+
+    // otpauth://totp/Example:me@example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        let rest = uri.strip_prefix("otpauth://")?;
+        let (kind, rest) = rest.split_once('/')?;
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        let mut secret = None;
+        let mut algorithm = Algorithm::Sha1;
+        let mut digits = 6;
+        let mut period = 30;
+        let mut counter = None;
+        let mut issuer = None;
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=')?;
+            let value = percent_decode(value)?;
+            match key.to_lowercase().as_str() {
+                "secret" => secret = Some(base32_decode(&value)?),
+                "algorithm" => algorithm = Algorithm::parse(&value)?,
+                "digits" => digits = value.parse().ok()?,
+                "period" => period = value.parse().ok()?,
+                "counter" => counter = Some(value.parse().ok()?),
+                "issuer" => issuer = Some(value),
+                // things like image urls that don't matter here
+                _ => {},
+            }
+        }
+
+        let method = match kind.to_lowercase().as_str() {
+            "totp" => Method::Totp { period },
+            "hotp" => Method::Hotp { counter: counter? },
+            _ => return None,
+        };
+        let mut otp = Otp::new(secret?, algorithm, digits, method)?;
+        let label = percent_decode(label)?;
+        if !label.is_empty() {
+            otp.label = Some(label);
+        }
+        otp.issuer = issuer;
+        Some(otp)
+    }
+
+    pub fn to_uri(&self) -> String {
+        let (kind, param) = match self.method {
+            Method::Totp { period } => ("totp", format!("period={}", period)),
+            Method::Hotp { counter } => ("hotp", format!("counter={}", counter)),
+        };
+        let mut uri = format!(
+            "otpauth://{}/{}?secret={}&algorithm={}&digits={}&{}",
+            kind, percent_encode(self.label.as_deref().unwrap_or("")), base32_encode(&self.secret), self.algorithm, self.digits, param,
+        );
+        if let Some(issuer) = &self.issuer {
+            uri.push_str(&format!("&issuer={}", percent_encode(issuer)));
+        }
+        uri
+    }
+
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    // the code for right now, time being seconds since the unix epoch
+    // for HOTP this is the code for the current counter, use `advance` after
+    pub fn code(&self, time: u64) -> String {
+        match self.method {
+            Method::Totp { period } => self.hotp(time / period),
+            Method::Hotp { counter } => self.hotp(counter),
+        }
+    }
+
+    // how many seconds the current TOTP code is good for
+    pub fn remaining(&self, time: u64) -> Option<u64> {
+        match self.method {
+            Method::Totp { period } => Some(period - time % period),
+            Method::Hotp { .. } => None,
+        }
+    }
+
+    // move on to the next HOTP code
+    pub fn advance(&mut self) {
+        if let Method::Hotp { counter } = &mut self.method {
+            *counter += 1;
+        }
+    }
+
+    pub fn hotp(&self, counter: u64) -> String {
+        let msg = counter.to_be_bytes();
+        let hash = match self.algorithm {
+            Algorithm::Sha1 => hmac::<Hmac<Sha1>>(&self.secret, &msg),
+            Algorithm::Sha256 => hmac::<Hmac<Sha256>>(&self.secret, &msg),
+            Algorithm::Sha512 => hmac::<Hmac<Sha512>>(&self.secret, &msg),
+        };
+        // dynamic truncation from RFC 4226
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let bin = u32::from_be_bytes([hash[offset] & 0x7f, hash[offset + 1], hash[offset + 2], hash[offset + 3]]);
+        let code = bin as u64 % 10u64.pow(self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+}
+
+fn hmac<M: Mac + KeyInit>(key: &[u8], msg: &[u8]) -> Vec<u8> {
+    let mut mac = <M as Mac>::new_from_slice(key).expect("hmac takes keys of any size");
+    mac.update(msg);
+    mac.finalize().into_bytes().to_vec()
+}
+
+const BASE32: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+// RFC 4648 base32, secrets are usually shown with spaces and without padding
+pub fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut bits = 0u64;
+    let mut count = 0;
+    let mut out = Vec::new();
+    for c in s.chars().filter(|c| !c.is_whitespace() && *c != '=' && *c != '-') {
+        let val = BASE32.iter().position(|b| *b as char == c.to_ascii_uppercase())?;
+        bits = (bits << 5) | val as u64;
+        count += 5;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    if out.is_empty() {
+        None
+    } else {
+        Some(out)
+    }
+}
+
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut bits = 0u64;
+    let mut count = 0;
+    let mut out = String::new();
+    for byte in data {
+        bits = (bits << 8) | *byte as u64;
+        count += 8;
+        while count >= 5 {
+            count -= 5;
+            out.push(BASE32[((bits >> count) & 0x1f) as usize] as char);
+        }
+    }
+    if count > 0 {
+        out.push(BASE32[((bits << (5 - count)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+                out.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes().map(|b| {
+        if b.is_ascii_alphanumeric() || b"-_.~@:".contains(&b) {
+            (b as char).to_string()
+        } else {
+            format!("%{:02X}", b)
+        }
+    }).collect()
+}
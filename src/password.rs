@@ -1,119 +1,888 @@
+use rand::{CryptoRng, Rng};
 use rand::seq::SliceRandom;
 use serde::{Serialize, Deserialize};
 
+// how many times generation starts over before giving up on a spec
+const ATTEMPTS: usize = 100;
+
 // specification for password requirements
 // for the most part it is very likely that requirements are met just through
 // randomness, but just have some enforcement
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Password {
     generic: Choice<char>,
-    digit: bool,
-    upper: bool,
-    lower: bool,
-    alpha: bool,
-    symbol: bool,
+    digit: Count,
+    upper: Count,
+    lower: Count,
+    alpha: Count,
+    symbol: Count,
     length: u32, //u32 just in case someone allows for a password that is billions of characters
                  //long lol
     extra: Vec<Choice<char>>, // weird extra constraints for stupid password things
+    #[serde(default)]
+    exclude: Option<Choice<char>>, // characters that should never show up
+    #[serde(default)]
+    max_consecutive: Option<u32>, // how many of the same character can be in a row
+    #[serde(default)]
+    no_sequences: bool, // no runs like abc or 321
+    #[serde(default)]
+    no_repeats: bool, // every character only shows up once
+}
+
+// how many characters of a class a password needs, at least `min` and if
+// there is a `max` no more than that
+// older vaults just stored a bool for each class, so those still deserialize
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "CountRepr")]
+pub struct Count {
+    pub min: u32,
+    pub max: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CountRepr {
+    Flag(bool),
+    Count { min: u32, max: Option<u32> },
+}
+
+impl From<CountRepr> for Count {
+    fn from(value: CountRepr) -> Self {
+        match value {
+            CountRepr::Flag(b) => Count::from(b),
+            CountRepr::Count { min, max } => Count { min, max },
+        }
+    }
+}
+
+impl From<bool> for Count {
+    fn from(value: bool) -> Self {
+        if value { Count::at_least(1) } else { Count::default() }
+    }
+}
+
+impl Count {
+    pub fn at_least(min: u32) -> Self {
+        Count { min, max: None }
+    }
+
+    pub fn between(min: u32, max: u32) -> Self {
+        Count { min, max: Some(max) }
+    }
+
+    // the same class showing up twice, e.g. digit+digit, means needing both
+    fn join(self, other: Count) -> Self {
+        let max = match (self.max, other.max) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        Count { min: self.min + other.min, max }
+    }
+
+    fn allows(&self, current: u32) -> bool {
+        match self.max {
+            Some(max) => current < max,
+            None => true,
+        }
+    }
+
+    // how many more than the minimum there is room for, given `used` of them
+    // are already taken by something else that's required, None is no limit
+    fn room(&self, used: u32) -> Option<Option<u32>> {
+        match self.max {
+            Some(max) => max.checked_sub(self.min + used).map(Some),
+            None => Some(None),
+        }
+    }
 }
 
 impl Password {
-    pub fn new(generic: Choice<char>, digit: bool, upper: bool, lower: bool, alpha: bool, symbol: bool, length: u32, extra: Vec<Choice<char>>) -> Option<Self> {
-        // the length of the password must be the number of 
-        let mut tot = extra.len();
-        if digit { tot += 1 };
-        if upper { tot += 1 };
-        if lower { tot += 1 };
-        if alpha { tot += 1 };
-        if symbol { tot += 1 };
-        if (length as usize) >= tot {
-            Some(Password { generic, digit, upper, lower, alpha, symbol, length, extra })
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(generic: Choice<char>, digit: Count, upper: Count, lower: Count, alpha: Count, symbol: Count, length: u32, extra: Vec<Choice<char>>) -> Option<Self> {
+        // the length of the password must be at least the number of required
+        // characters
+        let mut tot = extra.len() as u64;
+        for count in [&digit, &upper, &lower, &alpha, &symbol] {
+            if count.max.is_some_and(|max| max < count.min) {
+                return None;
+            }
+            tot += count.min as u64;
+        }
+        // upper and lower characters are also alpha characters
+        if alpha.max.is_some_and(|max| (max as u64) < upper.min as u64 + lower.min as u64 + alpha.min as u64) {
+            return None;
+        }
+        if (length as u64) < tot {
+            return None;
+        }
+        let password = Password { generic, digit, upper, lower, alpha, symbol, length, extra, exclude: None, max_consecutive: None, no_sequences: false, no_repeats: false };
+        if password.fillable() {
+            Some(password)
         } else {
             None
         }
     }
 
+    // a class or set without the excluded characters
+    fn without_excluded(&self, choice: &Choice<char>) -> Option<Choice<char>> {
+        match &self.exclude {
+            Some(exclude) => choice.subtract(exclude),
+            None => Some(choice.clone()),
+        }
+    }
+
+    // what each required set can actually use, leaving out characters from
+    // classes whose minimum already takes up their whole maximum
+    // a set spanning a few classes can end up squeezed into just one of them
+    fn open_sets(&self) -> Option<Vec<Choice<char>>> {
+        let mut full: Vec<Choice<char>> = self.classes().into_iter()
+            .filter(|(count, _)| count.max.is_some_and(|max| max <= count.min))
+            .map(|(_, class)| class)
+            .collect();
+        // upper and lower count against alpha's maximum as well
+        if self.alpha.max.is_some_and(|max| max <= self.upper.min + self.lower.min + self.alpha.min) {
+            full.push(alpha());
+        }
+        self.extra.iter().map(|set| {
+            let set = self.without_excluded(set)?;
+            Choice::new(set.iter().filter(|c| !full.iter().any(|class| class.contains(c))).copied().collect())
+        }).collect()
+    }
+
+    // whether the positions that aren't required can be filled from the
+    // allowed characters without going over any class's maximum
+    // required sets that only have characters from a capped class count
+    // against it too
+    fn fillable(&self) -> bool {
+        let generic = match self.without_excluded(&self.generic) {
+            Some(generic) => generic,
+            None => return false,
+        };
+        let sets = match self.open_sets() {
+            Some(sets) => sets,
+            None => return false,
+        };
+        let required = self.classes().iter().map(|(count, _)| count.min).sum::<u32>() + self.extra.len() as u32;
+        let free = self.length.saturating_sub(required) as u64;
+        let within = |class: &Choice<char>| sets.iter().filter(|set| set.iter().all(|c| class.contains(c))).count() as u32;
+        let has = |class: &Choice<char>| generic.iter().any(|c| class.contains(c));
+
+        // None is unlimited, the outer None is already over the maximum
+        let add = |a: Option<u64>, b: Option<u64>| a.zip(b).map(|(a, b)| a + b);
+        let least = |a: Option<u64>, b: Option<u64>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let room = |count: &Count, class: &Choice<char>| count.room(within(class)).map(|r| r.map(|r| r as u64));
+        let (digits, symbols, uppers, lowers) = match (room(&self.digit, &digit()), room(&self.symbol, &symbol()), room(&self.upper, &upper()), room(&self.lower, &lower())) {
+            (Some(d), Some(s), Some(u), Some(l)) => (d, s, u, l),
+            _ => return false,
+        };
+        // alpha's maximum covers upper and lower too, and alpha's minimum
+        // comes out of whatever room is left for letters
+        let alphas = match self.alpha.max {
+            Some(max) => match max.checked_sub(self.upper.min + self.lower.min + within(&alpha())) {
+                Some(room) => Some(room as u64),
+                None => return false,
+            },
+            None => None,
+        };
+        let letters = least(add(uppers, lowers), alphas);
+        if letters.is_some_and(|l| l < self.alpha.min as u64) {
+            return false;
+        }
+        let letters = letters.map(|l| l - self.alpha.min as u64);
+
+        // only what the allowed characters have can fill the free positions
+        let only = |room: Option<u64>, class: &Choice<char>| if has(class) { room } else { Some(0) };
+        let free_letters = least(add(only(uppers, &upper()), only(lowers, &lower())), letters);
+        let other = if generic.iter().any(|c| !c.is_ascii_alphanumeric() && !symbol().contains(c)) { None } else { Some(0) };
+        let (digits, symbols) = (only(digits, &digit()), only(symbols, &symbol()));
+
+        match add(add(add(digits, symbols), free_letters), other) {
+            Some(capacity) => capacity >= free,
+            None => true,
+        }
+    }
+
     pub fn standard() -> Self {
-        Password { generic: generic(), digit: false, upper: false, lower: false, alpha: false, symbol: false, length: 32, extra: Vec::new() }
+        Password { generic: generic(), digit: Count::default(), upper: Count::default(), lower: Count::default(), alpha: Count::default(), symbol: Count::default(), length: 32, extra: Vec::new(), exclude: None, max_consecutive: None, no_sequences: false, no_repeats: false }
     }
 
     // really simple parsing
     // example "upper+digit" => requires uppercase and a number
-    // maybe there is a use case for something more complicated like
-    // digit+digit => two numbers, but for now not a concern other than for the thing that requires
-    // that
-    // still no good idea for the weird extra things being specified that is also
-    // simple, maybe something like [1a, 7!] => requires either 1a or 7! to show up but that breaks
-    // length guarantees \shrug
+    // counts can be given as "digit*3" or "digit{3}" => at least three numbers,
+    // "symbol{,2}" => at most two symbols, "digit{2,4}" => two to four numbers
+    // repeating a class adds up the minimums, so "digit+digit" is the same as
+    // "digit*2"
+    // the weird extra things are given as a set of characters where at least
+    // one has to show up, "[!@#]" or "oneof(!@#)", and can have a count as
+    // well, "[!@#]*2" => two characters from !@#
+    // inside of a set a backslash escapes the next character, e.g. "[\]\\]"
     pub fn from_spec(allowed: Choice<char>, length: u32, pattern: String) -> Option<Self> {
         let generic = allowed;
-        let mut digit = false;
-        let mut upper = false;
-        let mut lower = false;
-        let mut alpha = false;
-        let mut symbol = false;
-        for s in pattern.split("+") {
-            match s {
-                "digit" => digit = true,
-                "upper" => upper = true,
-                "lower" => lower = true,
-                "alpha" => alpha = true,
-                "symbol" => symbol = true,
-                "" => {},
+        let mut digit = Count::default();
+        let mut upper = Count::default();
+        let mut lower = Count::default();
+        let mut alpha = Count::default();
+        let mut symbol = Count::default();
+        let mut extra = Vec::new();
+        for s in split_terms(&pattern)? {
+            let s = s.trim();
+            if s.is_empty() {
+                continue;
+            }
+            match parse_term(s)? {
+                (Term::Class("digit"), count) => digit = digit.join(count),
+                (Term::Class("upper"), count) => upper = upper.join(count),
+                (Term::Class("lower"), count) => lower = lower.join(count),
+                (Term::Class("alpha"), count) => alpha = alpha.join(count),
+                (Term::Class("symbol"), count) => symbol = symbol.join(count),
+                (Term::Set(chars), count) => {
+                    // a maximum on a set doesn't mean much since it isn't a
+                    // class that gets tracked
+                    if count.max.is_some() {
+                        return None;
+                    }
+                    let set = Choice::new(chars)?;
+                    for _ in 0..count.min {
+                        extra.push(set.clone());
+                    }
+                }
                 _ => return None,
             }
         }
-        Password::new(generic, digit, upper, lower, alpha, symbol, length, Vec::new())
+        Password::new(generic, digit, upper, lower, alpha, symbol, length, extra)
     }
 
-    pub fn generate(&self) -> String {
-        let mut vals : Vec<u32> = (0..self.length).collect();
-        vals.shuffle(&mut rand::thread_rng());
-
-        let mut res = Vec::new();
-        let digit = digit();
-        let upper = upper();
-        let lower = lower();
-        let alpha = alpha();
-        let symbol = symbol();
-        // checked on generation that length is fine, can be unsafe
-        if self.digit {
-            let item = vals.pop().unwrap();
-            res.push((item, digit.choose()));
+    // the passwordrules format that sites publish their policies in, e.g.
+    // "required: upper; required: digit; allowed: [-_]; max-consecutive: 2; minlength: 12; maxlength: 20"
+    // each required line needs one of its characters to show up, allowed
+    // adds to what the rest can be, and the length is kept between minlength
+    // and maxlength
+    pub fn from_rules(rules: &str, length: u32) -> Option<Self> {
+        let mut allowed: Vec<char> = Vec::new();
+        let mut digit = Count::default();
+        let mut upper = Count::default();
+        let mut lower = Count::default();
+        let mut symbol = Count::default();
+        let mut extra = Vec::new();
+        let mut max_consecutive = None;
+        let mut min_length = 0;
+        let mut max_length = u32::MAX;
+
+        for rule in rules.split(';') {
+            let rule = rule.trim();
+            if rule.is_empty() {
+                continue;
+            }
+            let (name, value) = rule.split_once(':')?;
+            let value = value.trim();
+            match name.trim().to_lowercase().as_str() {
+                "required" => {
+                    let classes = parse_rule_classes(value)?;
+                    for class in &classes {
+                        allowed.extend(class.choice()?.iter());
+                    }
+                    match classes.as_slice() {
+                        [RuleClass::Digit] => digit = digit.join(Count::at_least(1)),
+                        [RuleClass::Upper] => upper = upper.join(Count::at_least(1)),
+                        [RuleClass::Lower] => lower = lower.join(Count::at_least(1)),
+                        [RuleClass::Special] => symbol = symbol.join(Count::at_least(1)),
+                        _ => {
+                            let mut set = Vec::new();
+                            for class in &classes {
+                                set.extend(class.choice()?.iter());
+                            }
+                            extra.push(Choice::new(set)?);
+                        }
+                    }
+                }
+                "allowed" => {
+                    for class in parse_rule_classes(value)? {
+                        allowed.extend(class.choice()?.iter());
+                    }
+                }
+                "max-consecutive" => max_consecutive = Some(value.parse().ok()?),
+                "minlength" => min_length = value.parse().ok()?,
+                "maxlength" => max_length = value.parse().ok()?,
+                // the format says to ignore properties that aren't known so
+                // new ones can be added without breaking older parsers
+                _ => {}
+            }
         }
 
-        if self.upper {
-            let item = vals.pop().unwrap();
-            res.push((item, upper.choose()));
+        if min_length > max_length {
+            return None;
         }
+        allowed.sort();
+        allowed.dedup();
+        let generic = Choice::new(allowed).unwrap_or_else(generic);
+        let length = length.clamp(min_length, max_length);
+        let password = Password::new(generic, digit, upper, lower, Count::default(), symbol, length, extra)?;
+        match max_consecutive {
+            Some(max) => password.max_consecutive(max),
+            None => Some(password),
+        }
+    }
 
-        if self.lower {
-            let item = vals.pop().unwrap();
-            res.push((item, lower.choose()));
+    // never use any of the given characters, including for the required
+    // classes and sets
+    // fails if that leaves nothing to choose from for something that's needed
+    pub fn exclude(mut self, chars: &Choice<char>) -> Option<Self> {
+        self.generic = self.generic.subtract(chars)?;
+        for (count, class) in self.classes() {
+            if count.min > 0 {
+                class.subtract(chars)?;
+            }
+        }
+        self.extra = self.extra.iter().map(|extra| extra.subtract(chars)).collect::<Option<_>>()?;
+        match &mut self.exclude {
+            Some(exclude) => exclude.join(chars.clone()),
+            None => self.exclude = Some(chars.clone()),
         }
+        if !self.fillable() || !self.runs_fit() || (self.no_repeats && !self.distinct()) {
+            return None;
+        }
+        Some(self)
+    }
 
-        if self.alpha {
-            let item = vals.pop().unwrap();
-            res.push((item, alpha.choose()));
+    // the same spec with a different length and/or pattern, everything else
+    // like exclusions and repeat rules stays the same
+    pub fn respec(&self, length: Option<u32>, pattern: Option<String>) -> Option<Self> {
+        let length = length.unwrap_or(self.length);
+        let mut spec = match pattern {
+            Some(pattern) => Password::from_spec(self.generic.clone(), length, pattern)?,
+            None => Password::new(self.generic.clone(), self.digit, self.upper, self.lower, self.alpha, self.symbol, length, self.extra.clone())?,
+        };
+        if let Some(exclude) = &self.exclude {
+            spec = spec.exclude(exclude)?;
+        }
+        if let Some(max) = self.max_consecutive {
+            spec = spec.max_consecutive(max)?;
         }
+        spec.no_sequences = self.no_sequences;
+        if self.no_repeats {
+            spec = spec.no_repeats()?;
+        }
+        Some(spec)
+    }
 
-        if self.symbol {
-            let item = vals.pop().unwrap();
-            res.push((item, symbol.choose()));
+    // no more than `max` of the same character in a row
+    pub fn max_consecutive(mut self, max: u32) -> Option<Self> {
+        if max == 0 {
+            return None;
+        }
+        self.max_consecutive = Some(max);
+        if !self.runs_fit() {
+            return None;
+        }
+        Some(self)
+    }
+
+    // with only one character to fill the rest of the password with, the
+    // required characters are all there is to break it up into short enough
+    // runs, any more than one character can just take turns
+    fn runs_fit(&self) -> bool {
+        let Some(max) = self.max_consecutive else {
+            return true;
+        };
+        let generic = match self.without_excluded(&self.generic) {
+            Some(generic) => generic,
+            None => return false,
+        };
+        if generic.len() > 1 {
+            return true;
+        }
+        let only = *generic.iter().next().unwrap();
+        let required = self.classes().into_iter()
+            .map(|(count, class)| (count.min as usize, class))
+            .chain(self.extra.iter().map(|set| (1, set.clone())));
+        let (mut same, mut breaks, mut placed) = (0, 0, 0);
+        for (n, set) in required {
+            placed += n;
+            match self.without_excluded(&set) {
+                Some(set) if set.len() == 1 && set.contains(&only) => same += n,
+                _ => breaks += n,
+            }
+        }
+        let runs = self.length as usize - placed + same;
+        runs <= max as usize * (breaks + 1)
+    }
+
+    // no ascending or descending runs of three letters or digits
+    pub fn no_sequences(mut self) -> Self {
+        self.no_sequences = true;
+        self
+    }
+
+    // every character is different, so there needs to be enough of them
+    pub fn no_repeats(mut self) -> Option<Self> {
+        self.no_repeats = true;
+        if self.distinct() {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    // whether there are enough different characters for every class, every
+    // set and the free positions, not just enough overall
+    // required characters that could be drawn from the allowed set might use
+    // some of it up, so the allowed set has to cover everything except the
+    // ones that can't
+    fn distinct(&self) -> bool {
+        let size = |choice: &Choice<char>| self.without_excluded(choice).map_or(0, |c| c.len());
+        let mut avail = self.generic.clone();
+        for (count, class) in self.classes() {
+            if count.min as usize > size(&class) {
+                return false;
+            }
+            if count.min > 0 {
+                avail.join(class);
+            }
+        }
+        if (self.upper.min + self.lower.min + self.alpha.min) as usize > size(&alpha()) {
+            return false;
+        }
+        for set in &self.extra {
+            let inside = self.extra.iter().filter(|other| other.iter().all(|c| set.contains(c))).count();
+            if inside > size(set) {
+                return false;
+            }
+            avail.join(set.clone());
+        }
+        avail.avail.sort();
+        avail.avail.dedup();
+        if size(&avail) < self.length as usize {
+            return false;
+        }
+
+        let generic = match self.without_excluded(&self.generic) {
+            Some(generic) => generic,
+            None => return false,
+        };
+        let apart = |set: &Choice<char>| !set.iter().any(|c| generic.contains(c));
+        let elsewhere = self.classes().iter().filter(|(_, class)| apart(class)).map(|(count, _)| count.min as usize).sum::<usize>()
+            + self.extra.iter().filter(|set| apart(set)).count();
+        generic.len() + elsewhere >= self.length as usize
+    }
+
+    fn classes(&self) -> [(Count, Choice<char>); 5] {
+        [
+            (self.digit, digit()),
+            (self.upper, upper()),
+            (self.lower, lower()),
+            (self.alpha, alpha()),
+            (self.symbol, symbol()),
+        ]
+    }
+
+    // bits of entropy for a password made from this spec, every required
+    // character only has its own class to pick from and the rest pick from
+    // the whole allowed set
+    // the repeat and sequence rules take away a little bit, but not enough to
+    // matter here
+    pub fn entropy(&self) -> f64 {
+        let size = |choice: &Choice<char>| match &self.exclude {
+            Some(exclude) => choice.subtract(exclude).map_or(0, |c| c.len()),
+            None => choice.len(),
+        };
+        let mut bits = 0.0;
+        let mut rest = self.length as f64;
+        for (count, class) in self.classes() {
+            bits += count.min as f64 * (size(&class) as f64).log2();
+            rest -= count.min as f64;
+        }
+        for extra in &self.extra {
+            bits += (size(extra) as f64).log2();
+            rest -= 1.0;
+        }
+        bits + rest * (size(&self.generic) as f64).log2()
+    }
+
+    pub fn generate(&self) -> Option<String> {
+        self.generate_with(&mut rand::thread_rng())
+    }
+
+    // anything cryptographically secure works, mostly so tests can use a
+    // seeded rng and get the same password every time
+    // the checks when building the spec turn away the ones that obviously
+    // can't be filled, but the order things get placed in can still paint it
+    // into a corner with the sequence, consecutive and maximum rules, so it
+    // starts over when that happens and gives up if it keeps happening
+    pub fn generate_with<R: Rng + CryptoRng>(&self, rng: &mut R) -> Option<String> {
+        (0..ATTEMPTS).find_map(|_| self.attempt(rng))
+    }
+
+    fn attempt<R: Rng + CryptoRng>(&self, rng: &mut R) -> Option<String> {
+        let mut vals : Vec<usize> = (0..self.length as usize).collect();
+        vals.shuffle(rng);
+
+        let classes = self.classes();
+        let mut counts = [0; 5];
+
+        // the required characters get placed first and then the rest are
+        // filled in around them, checking the neighbours on both sides
+        let mut res = vec![None; self.length as usize];
+        // checked on generation that length is fine, can be unsafe
+        for (count, class) in &classes {
+            if count.min == 0 {
+                continue;
+            }
+            // exclusions are checked when they are added so something is left
+            let choice = match &self.exclude {
+                Some(exclude) => class.subtract(exclude).unwrap(),
+                None => class.clone(),
+            };
+            for _ in 0..count.min {
+                let item = vals.pop().unwrap();
+                res[item] = Some(self.pick(rng, &choice, item, &res, &classes, &mut counts)?);
+            }
         }
 
         for extra in &self.extra {
             let item = vals.pop().unwrap();
-            res.push((item, extra.choose()));
+            res[item] = Some(self.pick(rng, extra, item, &res, &classes, &mut counts)?);
         }
 
         for item in vals {
-            res.push((item, self.generic.choose()))
+            res[item] = Some(self.pick(rng, &self.generic, item, &res, &classes, &mut counts)?);
         }
 
-        res.sort_by(|a, b| a.0.cmp(&b.0));
+        res.into_iter().collect()
+    }
 
-        res.into_iter().map(|x| x.1).collect()
+    // choose a character for position i while keeping every class below its
+    // maximum and not breaking any of the repeat or sequence rules
+    // nothing fitting means this attempt is stuck
+    fn pick<R: Rng + CryptoRng>(&self, rng: &mut R, from: &Choice<char>, i: usize, res: &[Option<char>], classes: &[(Count, Choice<char>); 5], counts: &mut [u32; 5]) -> Option<char> {
+        let capped = |c: &char| classes.iter().zip(counts.iter()).all(|((count, class), n)| !class.contains(c) || count.allows(*n));
+        let c = *Choice::new(from.iter().filter(|c| self.fits(**c, i, res) && capped(c)).copied().collect())?.choose_with(rng);
+        for ((_, class), n) in classes.iter().zip(counts.iter_mut()) {
+            if class.contains(&c) {
+                *n += 1;
+            }
+        }
+        Some(c)
+    }
+
+    // whether c can go at position i given what has been placed so far
+    fn fits(&self, c: char, i: usize, res: &[Option<char>]) -> bool {
+        if self.no_repeats && res.contains(&Some(c)) {
+            return false;
+        }
+
+        if let Some(max) = self.max_consecutive {
+            let before = res[..i].iter().rev().take_while(|x| **x == Some(c)).count();
+            let after = res[i + 1..].iter().take_while(|x| **x == Some(c)).count();
+            if before + after + 1 > max as usize {
+                return false;
+            }
+        }
+
+        if self.no_sequences && c.is_ascii_alphanumeric() {
+            for start in i.saturating_sub(2)..=i {
+                let window: Option<Vec<char>> = (start..start + 3)
+                    .map(|j| if j == i { Some(c) } else { res.get(j).copied().flatten() })
+                    .collect();
+                if let Some(w) = window {
+                    if is_sequence(&w) {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        true
+    }
+}
+
+// three letters or digits going up or down by one, like abc or 321
+fn is_sequence(w: &[char]) -> bool {
+    if !w.iter().all(|c| c.is_ascii_alphanumeric()) {
+        return false;
+    }
+    let steps: Vec<i64> = w.windows(2).map(|p| p[1] as i64 - p[0] as i64).collect();
+    steps.iter().all(|s| *s == 1) || steps.iter().all(|s| *s == -1)
+}
+
+// the character classes that show up in passwordrules
+enum RuleClass {
+    Upper,
+    Lower,
+    Digit,
+    Special,
+    AsciiPrintable,
+    Set(Vec<char>),
+}
+
+impl RuleClass {
+    fn choice(&self) -> Option<Choice<char>> {
+        match self {
+            RuleClass::Upper => Some(upper()),
+            RuleClass::Lower => Some(lower()),
+            RuleClass::Digit => Some(digit()),
+            RuleClass::Special => Some(symbol()),
+            RuleClass::AsciiPrintable => Some(generic()),
+            RuleClass::Set(chars) => Choice::new(chars.clone()),
+        }
+    }
+}
+
+// a comma separated list of classes like "upper, digit, [-_]"
+// commas inside of brackets are part of the set
+fn parse_rule_classes(value: &str) -> Option<Vec<RuleClass>> {
+    let mut classes = Vec::new();
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        if let Some(inner) = rest.strip_prefix('[') {
+            // a ] right at the start is part of the set rather than closing it
+            let end = inner.chars().skip(1).position(|c| c == ']')? + 1;
+            let end = inner.char_indices().nth(end)?.0;
+            classes.push(RuleClass::Set(inner[..end].chars().collect()));
+            rest = inner[end + 1..].trim_start();
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            let class = match rest[..end].trim().to_lowercase().as_str() {
+                "upper" => RuleClass::Upper,
+                "lower" => RuleClass::Lower,
+                "digit" => RuleClass::Digit,
+                "special" => RuleClass::Special,
+                // there isn't any unicode support, so that's just treated the
+                // same as ascii
+                "ascii-printable" | "unicode" => RuleClass::AsciiPrintable,
+                _ => return None,
+            };
+            classes.push(class);
+            rest = &rest[end..];
+        }
+        rest = match rest.strip_prefix(',') {
+            Some(r) => r.trim_start(),
+            None if rest.is_empty() => rest,
+            None => return None,
+        };
+    }
+    Some(classes)
+}
+
+enum Term<'a> {
+    Class(&'a str),
+    Set(Vec<char>),
+}
+
+// split the pattern on the +'s that aren't inside of a set
+fn split_terms(pattern: &str) -> Option<Vec<&str>> {
+    let mut terms = Vec::new();
+    let mut start = 0;
+    let mut close = None;
+    let mut escaped = false;
+    for (i, c) in pattern.char_indices() {
+        match close {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(end) if c == end => close = None,
+            Some(_) => {},
+            None if c == '[' => close = Some(']'),
+            None if pattern[i..].starts_with("oneof(") => close = Some(')'),
+            None if c == '+' => {
+                terms.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            None => {},
+        }
+    }
+    if close.is_some() {
+        return None;
+    }
+    terms.push(&pattern[start..]);
+    Some(terms)
+}
+
+// split up something like "digit*3", "digit{2,4}", "[!@#]" or just "digit"
+fn parse_term(term: &str) -> Option<(Term<'_>, Count)> {
+    let (open, close) = if term.starts_with('[') {
+        ("[", ']')
+    } else if term.starts_with("oneof(") {
+        ("oneof(", ')')
+    } else {
+        let end = term.find(['*', '{']).unwrap_or(term.len());
+        return Some((Term::Class(&term[..end]), parse_count(&term[end..])?));
+    };
+
+    let mut chars = Vec::new();
+    let mut escaped = false;
+    for (i, c) in term.char_indices().skip(open.len()) {
+        if escaped {
+            chars.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == close {
+            return Some((Term::Set(chars), parse_count(&term[i + 1..])?));
+        } else {
+            chars.push(c);
+        }
+    }
+    None
+}
+
+// the count that comes after a class or set, "*3", "{3}", "{2,4}", "{,2}" or
+// nothing for just one
+fn parse_count(count: &str) -> Option<Count> {
+    if count.is_empty() {
+        return Some(Count::at_least(1));
+    }
+    if let Some(n) = count.strip_prefix('*') {
+        return Some(Count::at_least(n.parse().ok()?));
+    }
+    let inner = count.strip_prefix('{')?.strip_suffix('}')?;
+    match inner.split_once(',') {
+        Some((lo, hi)) => {
+            let min = if lo.is_empty() { 0 } else { lo.parse().ok()? };
+            let max = if hi.is_empty() { None } else { Some(hi.parse().ok()?) };
+            Some(Count { min, max })
+        }
+        None => Some(Count::at_least(inner.parse().ok()?)),
+    }
+}
+
+// words from the EFF large wordlist, the dice rolls are dropped since we aren't
+// actually rolling any dice
+const WORDLIST: &str = include_str!("eff_large_wordlist.txt");
+
+// specification for a diceware style passphrase
+// a lot easier to type out by hand and some places are fine with long passwords
+// but don't like symbols
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Passphrase {
+    words: u32,
+    separator: String,
+    capitalize: bool,
+    digit: bool,
+    symbol: bool,
+}
+
+impl Passphrase {
+    pub fn new(words: u32, separator: String, capitalize: bool, digit: bool, symbol: bool) -> Option<Self> {
+        let phrase = Passphrase { words, separator, capitalize, digit, symbol };
+        if words == 0 || (digit && phrase.without_separator(self::digit()).is_none()) || (symbol && phrase.without_separator(self::symbol()).is_none()) {
+            return None;
+        }
+        Some(phrase)
+    }
+
+    // the digit or symbol that gets added can't be part of the separator,
+    // otherwise it would split a word in two
+    fn without_separator(&self, class: Choice<char>) -> Option<Choice<char>> {
+        match Choice::new(self.separator.chars().collect()) {
+            Some(separator) => class.subtract(&separator),
+            None => Some(class),
+        }
+    }
+
+    pub fn standard() -> Self {
+        Passphrase { words: 6, separator: "-".to_string(), capitalize: false, digit: false, symbol: false }
+    }
+
+    // the words are where most of it comes from, the digit and symbol add
+    // their own choices plus which word they got stuck onto
+    pub fn entropy(&self) -> f64 {
+        let words = self.words as f64;
+        let mut bits = words * (wordlist().len() as f64).log2();
+        if self.digit {
+            bits += (self.without_separator(digit()).map_or(0, |c| c.len()) as f64).log2() + words.log2();
+        }
+        if self.symbol {
+            bits += (self.without_separator(symbol()).map_or(0, |c| c.len()) as f64).log2() + words.log2();
+        }
+        bits
+    }
+
+    pub fn generate(&self) -> String {
+        self.generate_with(&mut rand::thread_rng())
+    }
+
+    pub fn generate_with<R: Rng + CryptoRng>(&self, rng: &mut R) -> String {
+        let list = wordlist();
+        let mut words: Vec<String> = (0..self.words).map(|_| list.choose_with(rng).to_string()).collect();
+
+        if self.capitalize {
+            for word in words.iter_mut() {
+                let mut chars = word.chars();
+                if let Some(first) = chars.next() {
+                    *word = first.to_uppercase().chain(chars).collect();
+                }
+            }
+        }
+
+        // digits and symbols get tacked onto the end of a random word rather
+        // than being their own word so the passphrase stays readable
+        // new checks that something is left once the separator is taken out
+        if let (true, Some(digits)) = (self.digit, self.without_separator(digit())) {
+            let i = rng.gen_range(0..words.len());
+            words[i].push(*digits.choose_with(rng));
+        }
+
+        if let (true, Some(symbols)) = (self.symbol, self.without_separator(symbol())) {
+            let i = rng.gen_range(0..words.len());
+            words[i].push(*symbols.choose_with(rng));
+        }
+
+        words.join(&self.separator)
+    }
+}
+
+// the different kinds of specifications that can be used to generate a secret
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Spec {
+    Password(Password),
+    Passphrase(Passphrase),
+}
+
+impl Spec {
+    pub fn generate(&self) -> Option<String> {
+        self.generate_with(&mut rand::thread_rng())
+    }
+
+    // passphrases always work out, only passwords can fail
+    pub fn generate_with<R: Rng + CryptoRng>(&self, rng: &mut R) -> Option<String> {
+        match self {
+            Spec::Password(p) => p.generate_with(rng),
+            Spec::Passphrase(p) => Some(p.generate_with(rng)),
+        }
+    }
+
+    // for a passphrase the length is the number of words and there is no
+    // pattern
+    pub fn respec(&self, length: Option<u32>, pattern: Option<String>) -> Option<Self> {
+        match self {
+            Spec::Password(p) => Some(p.respec(length, pattern)?.into()),
+            Spec::Passphrase(_) if pattern.is_some() => None,
+            Spec::Passphrase(p) => {
+                let words = length.unwrap_or(p.words);
+                Some(Passphrase::new(words, p.separator.clone(), p.capitalize, p.digit, p.symbol)?.into())
+            }
+        }
+    }
+
+    pub fn entropy(&self) -> f64 {
+        match self {
+            Spec::Password(p) => p.entropy(),
+            Spec::Passphrase(p) => p.entropy(),
+        }
+    }
+}
+
+impl From<Password> for Spec {
+    fn from(value: Password) -> Self {
+        Spec::Password(value)
+    }
+}
+
+impl From<Passphrase> for Spec {
+    fn from(value: Passphrase) -> Self {
+        Spec::Passphrase(value)
     }
 }
 
@@ -125,7 +894,7 @@ pub struct Choice<T> {
 
 impl<T> Choice<T> {
     pub fn new(avail: Vec<T>) -> Option<Self> {
-        if avail.len() > 0 {
+        if !avail.is_empty() {
             Some(Choice { avail })
         } else {
             None
@@ -133,7 +902,24 @@ impl<T> Choice<T> {
     }
 
     pub fn choose(&self) -> &T {
-        self.avail.choose(&mut rand::thread_rng()).unwrap()
+        self.choose_with(&mut rand::thread_rng())
+    }
+
+    pub fn choose_with<R: Rng + CryptoRng>(&self, rng: &mut R) -> &T {
+        self.avail.choose(rng).unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.avail.len()
+    }
+
+    // always false, a Choice is never empty
+    pub fn is_empty(&self) -> bool {
+        self.avail.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.avail.iter()
     }
 
     fn join(&mut self, other: Choice<T>) {
@@ -141,6 +927,17 @@ impl<T> Choice<T> {
     }
 }
 
+impl<T: PartialEq + Clone> Choice<T> {
+    pub fn contains(&self, item: &T) -> bool {
+        self.avail.contains(item)
+    }
+
+    // everything that isn't in other, None when nothing is left
+    pub fn subtract(&self, other: &Choice<T>) -> Option<Self> {
+        Choice::new(self.avail.iter().filter(|x| !other.contains(x)).cloned().collect())
+    }
+}
+
 pub fn upper() -> Choice<char> {
    Choice::new(('A'..='Z').collect()).unwrap()
 }
@@ -171,10 +968,17 @@ pub fn symbol() -> Choice<char> {
     ]).unwrap()
 }
 
+// characters that are easy to mix up when reading them off of a screen
+pub fn ambiguous() -> Choice<char> {
+    Choice::new(vec!['0', 'O', 'o', '1', 'l', 'I', '|']).unwrap()
+}
+
 pub fn generic() -> Choice<char> {
     let mut x = alpha_num();
     x.join(symbol());
     x
 }
 
-
+pub fn wordlist() -> Choice<&'static str> {
+    Choice::new(WORDLIST.lines().filter_map(|line| line.split('\t').nth(1)).collect()).unwrap()
+}
@@ -0,0 +1,37 @@
+use rand::Rng;
+use rand::distributions::{Distribution, Standard};
+
+// single random characters from one of the basic classes, mostly useful for
+// `rand::random::<Digit>()` style one-offs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Digit {
+    pub val: char,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Uppercase {
+    pub val: char,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lowercase {
+    pub val: char,
+}
+
+impl Distribution<Digit> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Digit {
+        Digit { val: rng.gen_range('0'..='9') }
+    }
+}
+
+impl Distribution<Uppercase> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Uppercase {
+        Uppercase { val: rng.gen_range('A'..='Z') }
+    }
+}
+
+impl Distribution<Lowercase> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Lowercase {
+        Lowercase { val: rng.gen_range('a'..='z') }
+    }
+}
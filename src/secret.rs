@@ -0,0 +1,67 @@
+/*
+ * Holding onto passwords and keys without leaving them lying around
+ * the memory gets locked so it can't be swapped out (when the system lets us)
+ * and is wiped when the secret is dropped
+ * locks aren't counted, unlocking one secret's pages would unlock any other
+ * secret sharing them, so the pages just stay locked until the process exits
+ */
+
+use std::fmt::Debug;
+use std::ops::Deref;
+
+use zeroize::Zeroize;
+
+// there is no way to get at the inside mutably, that way the buffer that got
+// locked is the one that stays in use
+pub struct Secret<T: Zeroize + AsRef<[u8]>> {
+    inner: T,
+}
+
+impl<T: Zeroize + AsRef<[u8]>> Secret<T> {
+    pub fn new(inner: T) -> Self {
+        let bytes = inner.as_ref();
+        if !bytes.is_empty() {
+            unsafe { libc::mlock(bytes.as_ptr() as *const libc::c_void, bytes.len()) };
+        }
+        Secret { inner }
+    }
+}
+
+impl<T: Zeroize + AsRef<[u8]>> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.inner.zeroize();
+    }
+}
+
+impl<T: Zeroize + AsRef<[u8]>> Deref for Secret<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+// never print what's inside
+impl<T: Zeroize + AsRef<[u8]>> Debug for Secret<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+impl From<String> for Secret<String> {
+    fn from(value: String) -> Self {
+        Secret::new(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Secret::new(value.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Secret<Vec<u8>> {
+    fn from(value: Vec<u8>) -> Self {
+        Secret::new(value)
+    }
+}
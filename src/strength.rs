@@ -0,0 +1,377 @@
+/*
+ * Rough password strength estimates
+ * a spec knows how it was generated so it can give an exact entropy, but a
+ * password someone typed in needs to be picked apart looking for the things
+ * people actually do like words, keyboard walks and dates
+ */
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::password::wordlist;
+
+// some of the most common passwords, most common first
+const COMMON: &[&str] = &[
+    "123456", "password", "12345678", "qwerty", "123456789", "12345", "1234", "111111", "1234567", "dragon",
+    "123123", "baseball", "abc123", "football", "monkey", "letmein", "696969", "shadow", "master", "666666",
+    "qwertyuiop", "123321", "mustang", "1234567890", "michael", "654321", "superman", "1qaz2wsx", "7777777", "121212",
+    "000000", "qazwsx", "123qwe", "killer", "trustno1", "jordan", "jennifer", "zxcvbnm", "asdfgh", "hunter",
+    "buster", "soccer", "harley", "batman", "andrew", "tigger", "sunshine", "iloveyou", "charlie", "robert",
+    "thomas", "hockey", "ranger", "daniel", "starwars", "112233", "george", "computer", "michelle", "jessica",
+    "pepper", "zxcvbn", "555555", "11111111", "131313", "freedom", "777777", "pass", "maggie", "159753",
+    "aaaaaa", "ginger", "princess", "joshua", "cheese", "amanda", "summer", "love", "ashley", "nicole",
+    "chelsea", "matthew", "access", "yankees", "987654321", "dallas", "austin", "thunder", "taylor", "matrix",
+    "admin", "welcome", "login", "changeme", "secret", "default", "root", "guest", "hello", "whatever",
+];
+
+// unshifted keys of a US keyboard, one row at a time
+const KEYBOARD: &[&str] = &["`1234567890-=", "qwertyuiop[]\\", "asdfghjkl;'", "zxcvbnm,./"];
+const SHIFTED: &[&str] = &["~!@#$%^&*()_+", "QWERTYUIOP{}|", "ASDFGHJKL:\"", "ZXCVBNM<>?"];
+
+// common substitutions, only the first match for each character
+const LEET: &[(char, char)] = &[('4', 'a'), ('@', 'a'), ('3', 'e'), ('1', 'i'), ('!', 'i'), ('0', 'o'), ('5', 's'), ('$', 's'), ('7', 't'), ('+', 't')];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rating {
+    VeryWeak,
+    Weak,
+    Reasonable,
+    Strong,
+    VeryStrong,
+}
+
+impl Rating {
+    pub fn from_entropy(bits: f64) -> Self {
+        if bits < 28.0 {
+            Rating::VeryWeak
+        } else if bits < 50.0 {
+            Rating::Weak
+        } else if bits < 70.0 {
+            Rating::Reasonable
+        } else if bits < 100.0 {
+            Rating::Strong
+        } else {
+            Rating::VeryStrong
+        }
+    }
+}
+
+impl Display for Rating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rating::VeryWeak => "very weak".fmt(f),
+            Rating::Weak => "weak".fmt(f),
+            Rating::Reasonable => "reasonable".fmt(f),
+            Rating::Strong => "strong".fmt(f),
+            Rating::VeryStrong => "very strong".fmt(f),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Dictionary,
+    Keyboard,
+    Sequence,
+    Repeat,
+    Date,
+    Bruteforce,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kind::Dictionary => "dictionary word".fmt(f),
+            Kind::Keyboard => "keyboard walk".fmt(f),
+            Kind::Sequence => "sequence".fmt(f),
+            Kind::Repeat => "repeated character".fmt(f),
+            Kind::Date => "date".fmt(f),
+            Kind::Bruteforce => "random characters".fmt(f),
+        }
+    }
+}
+
+// a piece of the password and how it would most likely be guessed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub token: String,
+    pub kind: Kind,
+    pub entropy: f64,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Strength {
+    pub entropy: f64,
+    pub matches: Vec<Match>,
+}
+
+impl Strength {
+    pub fn rating(&self) -> Rating {
+        Rating::from_entropy(self.entropy)
+    }
+}
+
+// split the password into the cheapest set of matches to guess, anything that
+// isn't part of a pattern is treated as random characters
+pub fn estimate(password: &str) -> Strength {
+    let chars: Vec<char> = password.chars().collect();
+    let n = chars.len();
+    let brute = cardinality(&chars).log2();
+
+    let mut found = Vec::new();
+    found.extend(dictionary_matches(&chars));
+    found.extend(keyboard_matches(&chars));
+    found.extend(sequence_matches(&chars));
+    found.extend(repeat_matches(&chars));
+    found.extend(date_matches(&chars));
+
+    // best[i] is the lowest entropy for the first i characters
+    let mut best = vec![0.0; n + 1];
+    let mut back: Vec<Option<usize>> = vec![None; n + 1];
+    for i in 1..=n {
+        best[i] = best[i - 1] + brute;
+        for (j, m) in found.iter().enumerate() {
+            if m.end == i && best[m.start] + m.entropy < best[i] {
+                best[i] = best[m.start] + m.entropy;
+                back[i] = Some(j);
+            }
+        }
+    }
+
+    let mut matches = Vec::new();
+    let mut i = n;
+    while i > 0 {
+        match back[i] {
+            Some(j) => {
+                matches.push(found[j].clone());
+                i = found[j].start;
+            }
+            None => {
+                let mut start = i - 1;
+                while start > 0 && back[start].is_none() {
+                    start -= 1;
+                }
+                let len = i - start;
+                matches.push(Match { token: chars[start..i].iter().collect(), kind: Kind::Bruteforce, entropy: len as f64 * brute, start, end: i });
+                i = start;
+            }
+        }
+    }
+    matches.reverse();
+
+    Strength { entropy: best[n], matches }
+}
+
+// how many characters someone would need to try for each position based on
+// what kinds of characters show up at all
+fn cardinality(chars: &[char]) -> f64 {
+    let mut size = 0.0;
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        size += 10.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        size += 26.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        size += 26.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        size += 33.0;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        size += 100.0;
+    }
+    f64::max(size, 1.0)
+}
+
+fn ranked() -> HashMap<String, f64> {
+    let mut ranks = HashMap::new();
+    let words = wordlist();
+    for word in words.iter() {
+        ranks.insert(word.to_string(), words.len() as f64);
+    }
+    for (i, word) in COMMON.iter().enumerate() {
+        ranks.insert(word.to_string(), (i + 1) as f64);
+    }
+    ranks
+}
+
+fn dictionary_matches(chars: &[char]) -> Vec<Match> {
+    let ranks = ranked();
+    let longest = ranks.keys().map(|w| w.chars().count()).max().unwrap_or(0);
+    let mut matches = Vec::new();
+    for start in 0..chars.len() {
+        for end in (start + 3)..=chars.len().min(start + longest) {
+            let token = &chars[start..end];
+            let lower: String = token.iter().flat_map(|c| c.to_lowercase()).collect();
+            let unleet: String = lower.chars().map(|c| LEET.iter().find(|(l, _)| *l == c).map_or(c, |(_, r)| *r)).collect();
+            let reversed: String = lower.chars().rev().collect();
+
+            let (rank, extra) = if let Some(rank) = ranks.get(&lower) {
+                (*rank, 0.0)
+            } else if let Some(rank) = ranks.get(&unleet) {
+                let subs = lower.chars().zip(unleet.chars()).filter(|(a, b)| a != b).count();
+                (*rank, subs as f64)
+            } else if let Some(rank) = ranks.get(&reversed) {
+                (*rank, 1.0)
+            } else {
+                continue;
+            };
+            let entropy = rank.log2() + extra + case_entropy(token);
+            matches.push(Match { token: token.iter().collect(), kind: Kind::Dictionary, entropy, start, end });
+        }
+    }
+    matches
+}
+
+// capitalizing the first letter or everything is the usual thing, anything
+// else depends on how many letters could have been changed
+fn case_entropy(token: &[char]) -> f64 {
+    let upper = token.iter().filter(|c| c.is_uppercase()).count();
+    let lower = token.iter().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        return 0.0;
+    }
+    if lower == 0 || (upper == 1 && token[0].is_uppercase()) {
+        return 1.0;
+    }
+    let n = upper + lower;
+    let total: f64 = (1..=upper.min(lower)).map(|k| binomial(n, k)).sum();
+    total.log2()
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+fn key(c: char) -> Option<(i64, i64)> {
+    for (row, (plain, shifted)) in KEYBOARD.iter().zip(SHIFTED.iter()).enumerate() {
+        if let Some(col) = plain.chars().position(|k| k == c).or_else(|| shifted.chars().position(|k| k == c)) {
+            return Some((row as i64, col as i64));
+        }
+    }
+    None
+}
+
+// keys next to each other, including the rows above and below
+fn adjacent(a: char, b: char) -> bool {
+    match (key(a), key(b)) {
+        (Some((r1, c1)), Some((r2, c2))) => (r1, c1) != (r2, c2) && (r1 - r2).abs() <= 1 && (c1 - c2).abs() <= 1,
+        _ => false,
+    }
+}
+
+fn keyboard_matches(chars: &[char]) -> Vec<Match> {
+    let keys = KEYBOARD.iter().map(|row| row.len()).sum::<usize>() as f64;
+    runs(chars, adjacent).into_iter().map(|(start, end)| {
+        // about four keys to go to from each key
+        let entropy = keys.log2() + (end - start - 1) as f64 * 2.0;
+        Match { token: chars[start..end].iter().collect(), kind: Kind::Keyboard, entropy, start, end }
+    }).collect()
+}
+
+fn sequence_matches(chars: &[char]) -> Vec<Match> {
+    let step = |a: char, b: char| {
+        let same = (a.is_ascii_digit() && b.is_ascii_digit()) || (a.is_ascii_lowercase() && b.is_ascii_lowercase()) || (a.is_ascii_uppercase() && b.is_ascii_uppercase());
+        same && (b as i64 - a as i64).abs() == 1
+    };
+    let mut matches = Vec::new();
+    for (start, end) in runs(chars, step) {
+        // a run can switch direction partway, so split those up
+        let mut s = start;
+        for i in (start + 2)..=end {
+            if i == end || chars[i] as i64 - chars[i - 1] as i64 != chars[s + 1] as i64 - chars[s] as i64 {
+                if i - s >= 3 {
+                    let base = if chars[s].is_ascii_digit() { 10.0_f64 } else { 26.0 };
+                    let entropy = base.log2() + 1.0 + ((i - s) as f64).log2();
+                    matches.push(Match { token: chars[s..i].iter().collect(), kind: Kind::Sequence, entropy, start: s, end: i });
+                }
+                s = i - 1;
+            }
+        }
+    }
+    matches
+}
+
+fn repeat_matches(chars: &[char]) -> Vec<Match> {
+    runs(chars, |a, b| a == b).into_iter().map(|(start, end)| {
+        let entropy = cardinality(&chars[start..start + 1]).log2() + ((end - start) as f64).log2();
+        Match { token: chars[start..end].iter().collect(), kind: Kind::Repeat, entropy, start, end }
+    }).collect()
+}
+
+// stretches of at least three characters where every neighbouring pair
+// satisfies f
+fn runs(chars: &[char], f: impl Fn(char, char) -> bool) -> Vec<(usize, usize)> {
+    let mut res = Vec::new();
+    let mut start = 0;
+    for i in 1..=chars.len() {
+        if i == chars.len() || !f(chars[i - 1], chars[i]) {
+            if i - start >= 3 {
+                res.push((start, i));
+            }
+            start = i;
+        }
+    }
+    res
+}
+
+fn date_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    for start in 0..chars.len() {
+        for len in [4, 6, 8, 10] {
+            let end = start + len;
+            if end > chars.len() {
+                break;
+            }
+            if let Some(entropy) = date_entropy(&chars[start..end]) {
+                matches.push(Match { token: chars[start..end].iter().collect(), kind: Kind::Date, entropy, start, end });
+            }
+        }
+    }
+    matches
+}
+
+// years from 1900 to 2039 or full dates in the usual orders, with or without
+// separators
+fn date_entropy(token: &[char]) -> Option<f64> {
+    let years = 140.0_f64;
+    let number = |s: &[char]| -> Option<u32> {
+        if s.iter().all(|c| c.is_ascii_digit()) {
+            s.iter().collect::<String>().parse().ok()
+        } else {
+            None
+        }
+    };
+    let year = |y: u32, len: usize| (len == 2) || (1900..2040).contains(&y);
+    let valid = |d: u32, m: u32| (1..=31).contains(&d) && (1..=12).contains(&m);
+    let bits = |len: usize| if len == 2 { (31.0 * 12.0 * 100.0_f64).log2() } else { (31.0 * 12.0 * years).log2() };
+
+    if let Some(sep) = token.iter().find(|c| "-/._ ".contains(**c)) {
+        let parts: Vec<&[char]> = token.split(|c| c == sep).collect();
+        if parts.len() != 3 || parts.iter().any(|p| p.is_empty() || p.len() > 4) {
+            return None;
+        }
+        let nums: Vec<u32> = parts.iter().map(|p| number(p)).collect::<Option<_>>()?;
+        if parts[0].len() == 4 && year(nums[0], 4) && valid(nums[2], nums[1]) {
+            return Some(bits(4) + 2.0);
+        }
+        let y = parts[2].len();
+        if (y == 2 || y == 4) && year(nums[2], y) && (valid(nums[0], nums[1]) || valid(nums[1], nums[0])) {
+            return Some(bits(y) + 2.0);
+        }
+        return None;
+    }
+
+    number(token)?;
+    let num = |a: usize, b: usize| number(&token[a..b]).unwrap();
+    match token.len() {
+        4 if year(num(0, 4), 4) => Some(years.log2()),
+        6 if valid(num(0, 2), num(2, 4)) || valid(num(2, 4), num(0, 2)) || valid(num(4, 6), num(2, 4)) => Some(bits(2)),
+        8 if year(num(4, 8), 4) && (valid(num(0, 2), num(2, 4)) || valid(num(2, 4), num(0, 2))) => Some(bits(4)),
+        8 if year(num(0, 4), 4) && valid(num(6, 8), num(4, 6)) => Some(bits(4)),
+        _ => None,
+    }
+}
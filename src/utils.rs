@@ -1,9 +1,12 @@
-use std::{path::PathBuf, fs};
-
-use base64::{engine::general_purpose, Engine};
-use fernet::Fernet;
-use scrypt::Params;
+use std::{path::{Path, PathBuf}, fs, env, process, thread};
+use std::time::Duration;
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::os::unix::io::AsRawFd;
 
+use crate::cipher::Key;
+use crate::format::{Container, Header};
+use crate::secret::Secret;
 use crate::vault::VaultData;
 
 pub fn config_dir() -> PathBuf {
@@ -12,35 +15,161 @@ pub fn config_dir() -> PathBuf {
     path
 }
 
-pub fn write_file(path: PathBuf, contents: String) -> Result<(), anyhow::Error> {
-    if let Some(p) = path.parent() {
-        fs::create_dir_all(p)?;
+// whether anything written under path only lives in memory
+#[cfg(target_os = "linux")]
+fn in_memory(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let Ok(name) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(name.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+    stat.f_type as libc::c_long == libc::TMPFS_MAGIC
+}
+
+#[cfg(not(target_os = "linux"))]
+fn in_memory(_path: &Path) -> bool {
+    false
+}
+
+// somewhere to put decrypted things for a little while, only memory backed
+// locations unless writing to a disk was allowed, since wiping a file on a
+// disk doesn't reliably get rid of what was there
+fn private_tmp_dir(allow_disk: bool) -> Result<PathBuf, anyhow::Error> {
+    let base = [PathBuf::from("/dev/shm"), env::var("XDG_RUNTIME_DIR").map(PathBuf::from).unwrap_or_default()]
+        .into_iter()
+        .find(|p| p.is_dir() && in_memory(p));
+    let mut dir = match base {
+        Some(base) => base,
+        None if allow_disk => env::temp_dir(),
+        None => anyhow::bail!("No memory backed directory to edit in"),
+    };
+    dir.push(format!("turt-{}", process::id()));
+    fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    Ok(dir)
+}
+
+// overwrite the file before removing it so the contents don't stick around
+// it gets removed even if overwriting it fails
+fn wipe_file(path: &Path) -> Result<(), anyhow::Error> {
+    let res = (|| {
+        let len = fs::metadata(path)?.len();
+        let mut file = fs::OpenOptions::new().write(true).open(path)?;
+        file.write_all(&vec![0u8; len as usize])?;
+        file.sync_all()
+    })();
+    fs::remove_file(path)?;
+    Ok(res?)
+}
+
+// wipe everything in the directory, including whatever the editor left
+// behind like swap files, and then the directory itself
+fn wipe_dir(dir: &Path) -> Result<(), anyhow::Error> {
+    let mut res = Ok(());
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        // links just get removed, not followed
+        let kind = entry.file_type()?;
+        let wiped = if kind.is_dir() {
+            wipe_dir(&path)
+        } else if kind.is_file() {
+            wipe_file(&path)
+        } else {
+            fs::remove_file(&path).map_err(Into::into)
+        };
+        res = res.and(wiped);
     }
-    fs::write(path, contents)?;
-    Ok(())
+    fs::remove_dir(dir)?;
+    res
 }
 
-pub fn create_fernet(password: String, salt: String) -> Option<Fernet> {
-    let mut out = vec![0u8;32];
-    let _res = scrypt::scrypt(password.as_bytes(), salt.as_bytes(), &Params::new(16, 8, 1, 32).unwrap(), &mut out);
+// open up the user's editor on some text and get back what they saved
+pub fn edit_secret(initial: &str, allow_disk: bool) -> Result<String, anyhow::Error> {
+    let dir = private_tmp_dir(allow_disk)?;
+    let mut path = dir.clone();
+    path.push("note.txt");
+
+    let res = (|| {
+        let mut file = fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path)?;
+        file.write_all(initial.as_bytes())?;
+        drop(file);
+
+        let editor = env::var("VISUAL").or_else(|_| env::var("EDITOR")).unwrap_or("vi".to_string());
+        let mut parts = editor.split_whitespace();
+        let program = parts.next().unwrap_or("vi");
+        let status = process::Command::new(program).args(parts).arg(&path).status()?;
+        if !status.success() {
+            anyhow::bail!("Editor exited with {}", status);
+        }
+        Ok(fs::read_to_string(&path)?)
+    })();
+
+    let wiped = wipe_dir(&dir);
+    let text = res?;
+    wiped?;
+    Ok(text)
+}
 
-    let key = general_purpose::URL_SAFE.encode(&out);
+// written next to the real file and then renamed over it, so a crash or a
+// full disk leaves either the old contents or the new ones and never half of
+// each, the syncs make sure the rename doesn't land before the data does
+pub fn write_file(path: PathBuf, contents: impl AsRef<[u8]>) -> Result<(), anyhow::Error> {
+    let dir = match path.parent() {
+        Some(p) => p.to_path_buf(),
+        None => anyhow::bail!("No directory for {:?}", path),
+    };
+    fs::create_dir_all(&dir)?;
+    let mut tmp = path.clone().into_os_string();
+    tmp.push(".tmp");
 
-    fernet::Fernet::new(&key)
+    let res = (|| {
+        let mut file = fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(&tmp)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        fs::rename(&tmp, &path)?;
+        fs::File::open(&dir)?.sync_all()?;
+        Ok(())
+    })();
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    res
+}
+
+// an advisory lock on a vault's directory, held until the file gets dropped
+// another turt working on the same vault gets waited on for a little while
+pub fn lock_dir(dir: &Path) -> Result<fs::File, anyhow::Error> {
+    let mut path = dir.to_path_buf();
+    path.push("lock");
+    let file = fs::OpenOptions::new().write(true).create(true).truncate(false).mode(0o600).open(path)?;
+    for _ in 0..100 {
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+            return Ok(file);
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::WouldBlock {
+            return Err(err.into());
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    anyhow::bail!("Vault is in use by another turt, try again once it is done")
 }
 
-pub fn write_encrypted(fernet: Fernet, path: PathBuf, data: VaultData) -> Result<(), anyhow::Error> {
-    let content = serde_json::to_string(&data)?;
-    let encrypted = fernet.encrypt(content.as_bytes());
-    let res = write_file(path, encrypted)?;
-    Ok(res)
+// the header goes in front of the data and is authenticated along with it
+pub fn write_encrypted(key: &Key, path: PathBuf, header: &Header, data: &VaultData) -> Result<(), anyhow::Error> {
+    let content = Secret::new(serde_json::to_string(data)?);
+    let mut container = Container::new(header.clone())?;
+    container.ciphertext = key.encrypt(content.as_bytes(), container.prefix())?;
+    write_file(path, container.to_bytes())
 }
 
-pub fn read_encrypted(fernet: Fernet, path: PathBuf) -> Result<VaultData, anyhow::Error> {
-    let content = fs::read_to_string(path)?;
-    let decrypted = fernet.decrypt(&content)?;
-    let str = String::from_utf8(decrypted)?;
-    let val = serde_json::from_str(&str)?;
+pub fn read_encrypted(key: &Key, ciphertext: &[u8], aad: &[u8]) -> Result<VaultData, anyhow::Error> {
+    let decrypted = Secret::new(key.decrypt(ciphertext, aad)?);
+    let str = std::str::from_utf8(&decrypted)?;
+    let val = serde_json::from_str(str)?;
     Ok(val)
 }
 
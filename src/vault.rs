@@ -1,20 +1,75 @@
 
-use crate::utils::{write_encrypted, read_encrypted, write_file, create_fernet};
+use crate::utils::{write_encrypted, read_encrypted, write_file, lock_dir};
 use std::{fs, fmt::Display};
 use crate::utils::config_dir;
 use std::collections::HashMap;
-use std::path::PathBuf;
-use fernet::Fernet;
-use crate::password::Password;
-use rand::rngs::OsRng;
-use scrypt::password_hash::SaltString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::cipher::Key;
+use crate::format::{random_key, Container, Header, Slot};
+use crate::kdf::Kdf;
+use crate::member::{Identity, Member};
+use crate::keyfile;
+use crate::secret::Secret;
+use crate::otp::{Method, Otp};
+use crate::password::{Password, Passphrase, Spec};
+use rand::{CryptoRng, Rng};
 use serde::{Serialize, Deserialize};
-use anyhow;
+use zeroize::Zeroize;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum VaultItem {
     Generic(String),
     GeneratedPassword(String, Password),
+    GeneratedPassphrase(String, Passphrase),
+    Secret(String),
+    Note(String),
+    Otp(Otp),
+}
+
+// the values get wiped once they aren't needed anymore
+impl Drop for VaultItem {
+    fn drop(&mut self) {
+        match self {
+            VaultItem::Generic(s) | VaultItem::Secret(s) | VaultItem::Note(s) => s.zeroize(),
+            VaultItem::GeneratedPassword(s, _) | VaultItem::GeneratedPassphrase(s, _) => s.zeroize(),
+            VaultItem::Otp(_) => {},
+        }
+    }
+}
+
+// whether a field should be kept off of the screen, passwords always are even
+// though older vaults stored manually specified ones as generic items
+pub fn is_secret(field: &str, item: &VaultItem) -> bool {
+    field == "password" || !matches!(item, VaultItem::Generic(_))
+}
+
+impl VaultItem {
+    // generate a new value from the spec and keep the spec around with it
+    pub fn generated(spec: Spec) -> Result<Self, anyhow::Error> {
+        VaultItem::generated_with(spec, &mut rand::thread_rng())
+    }
+
+    pub fn generated_with<R: Rng + CryptoRng>(spec: Spec, rng: &mut R) -> Result<Self, anyhow::Error> {
+        let value = match spec.generate_with(rng) {
+            Some(value) => value,
+            None => anyhow::bail!("Couldn't generate a password that fits the specification"),
+        };
+        Ok(match spec {
+            Spec::Password(p) => VaultItem::GeneratedPassword(value, p),
+            Spec::Passphrase(p) => VaultItem::GeneratedPassphrase(value, p),
+        })
+    }
+
+    // the spec the value was generated from, if there was one
+    pub fn spec(&self) -> Option<Spec> {
+        match self {
+            VaultItem::Generic(_) | VaultItem::Secret(_) | VaultItem::Note(_) | VaultItem::Otp(_) => None,
+            VaultItem::GeneratedPassword(_, p) => Some(p.clone().into()),
+            VaultItem::GeneratedPassphrase(_, p) => Some(p.clone().into()),
+        }
+    }
 }
 
 impl Display for VaultItem {
@@ -22,26 +77,125 @@ impl Display for VaultItem {
         match self {
             VaultItem::Generic(s) => s.fmt(f),
             VaultItem::GeneratedPassword(s, _) => s.fmt(f),
+            VaultItem::GeneratedPassphrase(s, _) => s.fmt(f),
+            VaultItem::Secret(s) => s.fmt(f),
+            VaultItem::Note(s) => s.fmt(f),
+            VaultItem::Otp(otp) => otp.to_uri().fmt(f),
         }
     }
 }
 
+// an old value for a field and when it stopped being used
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct HistoryItem {
+    pub item: VaultItem,
+    pub replaced: u64, // seconds since the unix epoch
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct VaultData {
     data: HashMap<String, HashMap<String, VaultItem>>,
+    #[serde(default)]
+    history: HashMap<String, HashMap<String, Vec<HistoryItem>>>,
+    #[serde(default)]
+    removed: HashMap<String, Vec<String>>, // the fields a removed entry had
 }
 
 impl VaultData {
     fn new() -> Self {
-        VaultData {data: HashMap::new()}
+        VaultData {data: HashMap::new(), history: HashMap::new(), removed: HashMap::new()}
+    }
+
+    // put a field's old value into the history, newest last
+    fn archive(&mut self, entry: &str, field: &str, item: VaultItem) {
+        let replaced = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        self.history.entry(entry.to_string()).or_default()
+            .entry(field.to_string()).or_default()
+            .push(HistoryItem { item, replaced });
+    }
+
+    // replacing a field keeps the old value in the history
+    fn set_field(&mut self, entry: &str, field: &str, item: VaultItem) {
+        self.removed.remove(entry);
+        let old = self.data.entry(entry.to_string()).or_default().insert(field.to_string(), item.clone());
+        if let Some(old) = old {
+            if old != item {
+                self.archive(entry, field, old);
+            }
+        }
+    }
+
+    fn unset_field(&mut self, entry: &str, field: &str) -> Option<VaultItem> {
+        let old = self.data.get_mut(entry)?.remove(field)?;
+        self.archive(entry, field, old.clone());
+        Some(old)
     }
 
     fn add(&mut self, entry: String, value: HashMap<String, VaultItem>) {
-        self.data.insert(entry, value);
+        self.removed.remove(&entry);
+        if let Some(old) = self.data.insert(entry.clone(), value) {
+            for (field, item) in old {
+                if self.data[&entry].get(&field) != Some(&item) {
+                    self.archive(&entry, &field, item);
+                }
+            }
+        }
+    }
+
+    // the history sticks around so a removed entry can still be restored
+    fn delete(&mut self, entry: &str) -> Option<()> {
+        let old = self.data.remove(entry)?;
+        self.removed.insert(entry.to_string(), old.keys().cloned().collect());
+        for (field, item) in old {
+            self.archive(entry, &field, item);
+        }
+        Some(())
+    }
+
+    // bring back every field a removed entry had, as it was when removed
+    fn undelete(&mut self, entry: &str) -> Option<()> {
+        if self.data.contains_key(entry) {
+            return None;
+        }
+        let fields = self.removed.remove(entry)?;
+        let history = self.history.get_mut(entry)?;
+        let mut info = HashMap::new();
+        for field in fields {
+            if let Some(old) = history.get_mut(&field).and_then(|items| items.pop()) {
+                info.insert(field, old.item);
+            }
+        }
+        self.data.insert(entry.to_string(), info);
+        Some(())
+    }
+
+    // an entry and everything it used to be, gone for good
+    fn purge(&mut self, entry: &str) -> Option<()> {
+        let data = self.data.remove(entry);
+        let history = self.history.remove(entry);
+        self.removed.remove(entry);
+        data.map(|_| ()).or(history.map(|_| ()))
+    }
+
+    fn history(&self, entry: &str) -> Option<&HashMap<String, Vec<HistoryItem>>> {
+        self.history.get(entry)
     }
 
-    fn delete(&mut self, entry: &str) -> Option<HashMap<String, VaultItem>> {
-        self.data.remove(entry)
+    // swap the current value with one from the history, 1 being the most
+    // recently replaced value
+    // a removed entry only comes back whole, otherwise it would be missing
+    // everything but the one field
+    fn restore(&mut self, entry: &str, field: &str, version: usize) -> Option<()> {
+        if !self.data.contains_key(entry) {
+            return None;
+        }
+        let items = self.history.get_mut(entry)?.get_mut(field)?;
+        if version == 0 || version > items.len() {
+            return None;
+        }
+        let restored = items.remove(items.len() - version);
+        self.set_field(entry, field, restored.item);
+        Some(())
     }
 
     fn get(&self, entry: &str) -> Option<&HashMap<String, VaultItem>> {
@@ -49,53 +203,163 @@ impl VaultData {
     }
 
     fn entries(&self) -> Vec<String> {
-        self.data.clone().into_keys().collect()
+        self.data.keys().cloned().collect()
+    }
+}
+
+// an older copy of the vault, entries is None when it can't be opened
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Backup {
+    pub number: u32,
+    pub saved: u64, // seconds since the unix epoch
+    pub entries: Option<usize>,
+}
+
+// the hash of the keyfile a vault needs, from where it was given or where it
+// was when the vault was made if that was remembered
+fn read_keyfile(header: &Header, keyfile: Option<&Path>) -> Result<Option<Secret<Vec<u8>>>, anyhow::Error> {
+    match (header.uses_keyfile, keyfile, header.keyfile.as_deref()) {
+        (false, None, _) => Ok(None),
+        (false, Some(_), _) => anyhow::bail!("The vault doesn't use a keyfile"),
+        (true, Some(given), _) | (true, None, Some(given)) => Ok(Some(keyfile::read(given)?)),
+        (true, None, None) => anyhow::bail!("The vault needs its keyfile as well as the password"),
     }
 }
 
-#[derive(Clone)]
+// the vault's directory stays locked for as long as this is around, so nobody
+// else can write to it in between reading and writing
 pub struct Vault {
     pub id: String,
     pub path: PathBuf,
-    fernet: Fernet,
+    pub header: Header,
+    key: Key,
+    data_key: Secret<Vec<u8>>,
+    // the vault key the slots wrap, None when opened by a member's identity
+    unlock: Option<Secret<Vec<u8>>>,
+    // the slot whose password opened the vault
+    slot: Option<String>,
+    // the hash of the keyfile, kept to derive the new key when rekeying
+    keyfile: Option<Secret<Vec<u8>>>,
     pub data: VaultData,
+    _lock: Arc<fs::File>,
 }
 
 impl Vault {
     fn check(id: String) -> bool {
         let mut dir = config_dir();
         dir.push(id.clone());
+        let mut vault_file = dir.clone();
+        vault_file.push("vault");
+        vault_file.set_extension("turt");
+
         let mut data_file = dir.clone();
         data_file.push("data");
         data_file.set_extension("json");
+        dir.exists() && (vault_file.exists() || data_file.exists())
+    }
 
-        let mut salt_file = dir.clone();
-        salt_file.push("salt");
-        salt_file.set_extension("txt");
-        dir.exists() && data_file.exists() && salt_file.exists()
+    pub fn new(id: String, password: Secret<String>) -> Result<Vault, anyhow::Error> {
+        Vault::open(id, password, None)
     }
 
-    pub fn new(id: String, password: String) -> Result<Vault, anyhow::Error> {
+    // a vault made with a keyfile needs it as well as the password, it is
+    // looked for where it was when the vault was made if that was remembered
+    pub fn open(id: String, password: Secret<String>, keyfile: Option<&Path>) -> Result<Vault, anyhow::Error> {
         let mut dir = config_dir();
         dir.push(id.clone());
-        let mut data_file = dir.clone();
-        data_file.push("data");
-        data_file.set_extension("json");
+        let mut vault_file = dir.clone();
+        vault_file.push("vault");
+        vault_file.set_extension("turt");
 
-        let mut salt_file = dir.clone();
-        salt_file.push("salt");
-        salt_file.set_extension("txt");
+        if !Vault::check(id.clone()) {
+            anyhow::bail!("No vault named {}", id);
+        }
+        let lock = Arc::new(lock_dir(&dir)?);
+
+        if !vault_file.exists() {
+            if keyfile.is_some() {
+                anyhow::bail!("The vault {} doesn't use a keyfile", id);
+            }
+            return Vault::migrate(id, password, lock);
+        }
 
-        let salt = fs::read_to_string(salt_file)?;
+        let container = Container::from_bytes(&fs::read(&vault_file)?)?;
+        let keyfile = read_keyfile(&container.header, keyfile)?;
+        let (slot, unlock) = container.header.unlock(&password, keyfile.as_deref().map(|k| &k[..]))?;
+        let data_key = container.header.data_key(&unlock)?;
+        let mut vault = Vault::opened(id, vault_file, container, data_key, Some(unlock), keyfile, lock)?;
+        vault.slot = Some(slot);
+        Ok(vault)
+    }
 
-        let fernet = create_fernet(password.to_string(), salt.to_string()).expect("Failed to setup encryption");
-        let data = read_encrypted(fernet.clone(), data_file.clone())?; 
-        Ok(Vault { id, path: data_file, fernet, data })
+    // everything but getting the data key is the same however it's opened
+    fn opened(id: String, path: PathBuf, container: Container, data_key: Secret<Vec<u8>>, unlock: Option<Secret<Vec<u8>>>, keyfile: Option<Secret<Vec<u8>>>, lock: Arc<fs::File>) -> Result<Vault, anyhow::Error> {
+        let key = container.header.key_for(&data_key)?;
+        let data = read_encrypted(&key, &container.ciphertext, container.prefix())?;
+        Ok(Vault { id, path, header: container.header, key, data_key, unlock, slot: None, keyfile, data, _lock: lock })
     }
 
-    pub fn create(id: String, password: String) -> Result<Vault, anyhow::Error> {
+    // open with a key that has already been derived, like one from the agent
+    pub fn with_key(id: String, secret: &[u8]) -> Result<Vault, anyhow::Error> {
         let mut dir = config_dir();
         dir.push(id.clone());
+        let mut vault_file = dir.clone();
+        vault_file.push("vault");
+        vault_file.set_extension("turt");
+
+        if !vault_file.exists() {
+            anyhow::bail!("No vault named {}", id);
+        }
+        let lock = Arc::new(lock_dir(&dir)?);
+
+        let container = Container::from_bytes(&fs::read(&vault_file)?)?;
+        let data_key = container.header.data_key(secret)?;
+        Vault::opened(id, vault_file, container, data_key, Some(Secret::new(secret.to_vec())), None, lock)
+    }
+
+    // open as one of the vault's members, without the password
+    pub fn with_identity(id: String, identity: &Identity) -> Result<Vault, anyhow::Error> {
+        let mut dir = config_dir();
+        dir.push(id.clone());
+        let mut vault_file = dir.clone();
+        vault_file.push("vault");
+        vault_file.set_extension("turt");
+
+        if !vault_file.exists() {
+            anyhow::bail!("No vault named {}", id);
+        }
+        let lock = Arc::new(lock_dir(&dir)?);
+
+        let container = Container::from_bytes(&fs::read(&vault_file)?)?;
+        let data_key = identity.unwrap(&container.header.members)?;
+        Vault::opened(id, vault_file, container, data_key, None, None, lock)
+    }
+
+    // the vault key a password gives, only a slot that wraps the key gets
+    // checked, so whether it opens the vault is left to `with_key`
+    pub fn derive_key(id: String, password: Secret<String>, keyfile: Option<&Path>) -> Result<Secret<Vec<u8>>, anyhow::Error> {
+        let mut vault_file = config_dir();
+        vault_file.push(id.clone());
+        vault_file.push("vault");
+        vault_file.set_extension("turt");
+
+        if !vault_file.exists() {
+            anyhow::bail!("No vault named {}, older vaults need to be opened once before they can be unlocked", id);
+        }
+        let container = Container::from_bytes(&fs::read(&vault_file)?)?;
+        let keyfile = read_keyfile(&container.header, keyfile)?;
+        Ok(container.header.unlock(&password, keyfile.as_deref().map(|k| &k[..]))?.1)
+    }
+
+    // vaults used to be a data.json next to a salt.txt, these get moved into
+    // a single file when opened
+    fn migrate(id: String, password: Secret<String>, lock: Arc<fs::File>) -> Result<Vault, anyhow::Error> {
+        let mut dir = config_dir();
+        dir.push(id.clone());
+        let mut vault_file = dir.clone();
+        vault_file.push("vault");
+        vault_file.set_extension("turt");
+
         let mut data_file = dir.clone();
         data_file.push("data");
         data_file.set_extension("json");
@@ -104,34 +368,349 @@ impl Vault {
         salt_file.push("salt");
         salt_file.set_extension("txt");
 
-        let salt = SaltString::generate(&mut OsRng); 
-        write_file(salt_file, salt.to_string())?;
+        let ciphertext = fs::read(&data_file)?;
+        let header = Header::legacy(fs::read_to_string(&salt_file)?);
+        let (slot, unlock) = header.unlock(&password, None)?;
+        let key = header.key_from(&unlock)?;
+        let data = read_encrypted(&key, &ciphertext, &[])?;
+
+        let data_key = Secret::new(unlock.to_vec());
+        let vault = Vault { id, path: vault_file, header, key, data_key, unlock: Some(unlock), slot: Some(slot), keyfile: None, data, _lock: lock };
+        vault.write()?;
+        fs::remove_file(data_file)?;
+        fs::remove_file(salt_file)?;
+        Ok(vault)
+    }
+
+    // the slot that opened the vault gets the new password with a fresh salt,
+    // the data key stays the same
+    pub fn change_password(&mut self, password: Secret<String>) -> Result<Vec<u32>, anyhow::Error> {
+        let kdf = self.opened_slot()?.kdf;
+        self.new_password(password, kdf)
+    }
+
+    // move to different key derivation parameters, the password is needed
+    // again since the key can't be derived without it
+    pub fn upgrade_kdf(&mut self, password: Secret<String>, kdf: Kdf) -> Result<Vec<u32>, anyhow::Error> {
+        self.new_password(password, kdf)
+    }
+
+    // the slot whose password opened the vault
+    pub fn slot(&self) -> Option<&Slot> {
+        self.header.slots.iter().find(|s| Some(&s.name) == self.slot.as_ref())
+    }
+
+    fn opened_slot(&self) -> Result<&Slot, anyhow::Error> {
+        match self.slot() {
+            Some(slot) => Ok(slot),
+            None => anyhow::bail!("The vault wasn't opened with a password, so there's no telling which one to change"),
+        }
+    }
+
+    // vaults from before slots used the key from their one password for
+    // everything, they get a new vault key and data key rather than keeping
+    // the one the old password gives
+    // the keyfile stays the same, so one opened with just the key can't get a
+    // new password if it has a keyfile
+    fn new_password(&mut self, password: Secret<String>, kdf: Kdf) -> Result<Vec<u32>, anyhow::Error> {
+        let name = self.opened_slot()?.name.clone();
+        let keyfile = self.keyfile.as_deref().map(|k| &k[..]);
+        let mut header = self.header.clone();
+        if self.is_legacy() {
+            let unlock = random_key();
+            header.set_slot(&name, kdf, &password, keyfile, &unlock)?;
+            return self.rekey(header, unlock);
+        }
+        header.set_slot(&name, kdf, &password, keyfile, &self.unlock()?)?;
+        self.rewrite(header)
+    }
+
+    fn is_legacy(&self) -> bool {
+        self.header.slots.iter().any(|s| !s.is_wrapped())
+    }
+
+    // another password that opens the vault, for giving out without sharing
+    // the usual one
+    pub fn add_slot(&mut self, name: &str, password: Secret<String>, kdf: Kdf) -> Result<Vec<u32>, anyhow::Error> {
+        if self.header.slots.iter().any(|s| s.name == name) {
+            anyhow::bail!("{} already has a slot named {}", self.id, name);
+        }
+        let keyfile = self.keyfile.as_deref().map(|k| &k[..]);
+        let mut header = self.header.clone();
+        if self.is_legacy() {
+            let unlock = random_key();
+            header.wrap_legacy(&self.unlock()?, &unlock)?;
+            header.set_slot(name, kdf, &password, keyfile, &unlock)?;
+            return self.rekey(header, unlock);
+        }
+        header.set_slot(name, kdf, &password, keyfile, &self.unlock()?)?;
+        self.rewrite(header)
+    }
+
+    // the slot's password stops working, though anyone who already opened the
+    // vault with it could have kept the vault key
+    pub fn remove_slot(&mut self, name: &str) -> Result<Vec<u32>, anyhow::Error> {
+        let mut header = self.header.clone();
+        header.slots.retain(|s| s.name != name);
+        if header.slots.len() == self.header.slots.len() {
+            anyhow::bail!("{} has no slot named {}", self.id, name);
+        }
+        if header.slots.is_empty() {
+            anyhow::bail!("{} is the only slot, a vault needs at least one password", name);
+        }
+        if self.slot.as_deref() == Some(name) {
+            self.slot = None;
+        }
+        self.rewrite(header)
+    }
+
+    // the vault key, needed for anything that changes the data key or slots
+    fn unlock(&self) -> Result<Secret<Vec<u8>>, anyhow::Error> {
+        match &self.unlock {
+            Some(unlock) => Ok(Secret::new(unlock.to_vec())),
+            None => anyhow::bail!("The vault password is needed to change the vault's key"),
+        }
+    }
+
+    // anyone with the identity for the public key can open the vault from then
+    // on, vaults that are still encrypted with the key from the password get a
+    // data key of their own first so the member never sees that key
+    pub fn add_member(&mut self, public: &str) -> Result<Vec<u32>, anyhow::Error> {
+        let member = Member::wrap(public, &self.data_key)?;
+        if self.header.members.iter().any(|m| m.public == member.public) {
+            anyhow::bail!("{} is already a member of {}", public, self.id);
+        }
+        if self.header.wrapped.is_none() {
+            let mut header = self.header.clone();
+            header.members.push(member);
+            return self.rekey(header, self.unlock()?);
+        }
+        self.header.members.push(member);
+        self.write()?;
+        Ok(Vec::new())
+    }
+
+    // the data key gets replaced, otherwise whoever was removed could still
+    // use the one they had unwrapped
+    pub fn remove_member(&mut self, public: &str) -> Result<Vec<u32>, anyhow::Error> {
+        let public = public.trim();
+        let mut header = self.header.clone();
+        header.members.retain(|m| m.public != public);
+        if header.members.len() == self.header.members.len() {
+            anyhow::bail!("{} isn't a member of {}", public, self.id);
+        }
+        self.rekey(header, self.unlock()?)
+    }
+
+    // a new data key for the header, the header and data live in the same
+    // file so swapping it out is enough to change the key without anything
+    // being left half done
+    // the backups get moved over to the new key too, otherwise an old password
+    // or a removed member would still open them
+    fn rekey(&mut self, mut header: Header, unlock: Secret<Vec<u8>>) -> Result<Vec<u32>, anyhow::Error> {
+        let data_key = header.seal(&unlock)?;
+        let backups = self.backup_numbers()?;
+        let old = self.replace(header, data_key)?;
+        self.unlock = Some(unlock);
+        Ok(self.move_backups(&old, backups, |_| {}))
+    }
+
+    // a new header with the same data key, the backups get it too so a
+    // removed or changed password doesn't still open them
+    fn rewrite(&mut self, header: Header) -> Result<Vec<u32>, anyhow::Error> {
+        let data_key = Secret::new(self.data_key.to_vec());
+        let backups = self.backup_numbers()?;
+        let old = self.replace(header, data_key)?;
+        Ok(self.move_backups(&old, backups, |_| {}))
+    }
+
+    // the vault itself moves over first, giving back the key it had before
+    fn replace(&mut self, header: Header, data_key: Secret<Vec<u8>>) -> Result<Key, anyhow::Error> {
+        let key = header.key_for(&data_key)?;
+        write_encrypted(&key, self.path.clone(), &header, &self.data)?;
+        self.header = header;
+        self.data_key = data_key;
+        Ok(std::mem::replace(&mut self.key, key))
+    }
 
-        let fernet = create_fernet(password.to_string(), salt.to_string()).expect("Failed to setup encryption");
+    // backups the old key can't open are left as they are rather than thrown
+    // away, they could be the only copy of something, so their numbers get
+    // handed back for whoever asked to know they didn't move over
+    // the vault has already moved by now, so a backup that fails to be read
+    // or written is one of those too rather than an error
+    // anything else that needs to change in every backup gets done on the way
+    fn move_backups(&self, old: &Key, numbers: Vec<u32>, change: impl Fn(&mut VaultData)) -> Vec<u32> {
+        let mut skipped = Vec::new();
+        for number in numbers {
+            let path = self.backup_file(number);
+            let moved = (|| {
+                let saved = fs::metadata(&path)?.modified()?;
+                let container = Container::from_bytes(&fs::read(&path)?)?;
+                let mut data = read_encrypted(old, &container.ciphertext, container.prefix())?;
+                change(&mut data);
+                write_encrypted(&self.key, path.clone(), &self.header, &data)?;
+                // only the order is off if this doesn't work
+                let _ = fs::File::options().write(true).open(&path).and_then(|file| file.set_modified(saved));
+                Ok::<(), anyhow::Error>(())
+            })();
+            if moved.is_err() {
+                skipped.push(number);
+            }
+        }
+        skipped
+    }
+
+    pub fn create(id: String, password: Secret<String>) -> Result<Vault, anyhow::Error> {
+        Vault::create_with(id, password, Kdf::default(), None, false)
+    }
+
+    // remembering where the keyfile is means not having to give it every time,
+    // but anyone who can read the vault file can see where it is
+    pub fn create_with(id: String, password: Secret<String>, kdf: Kdf, keyfile: Option<&Path>, remember: bool) -> Result<Vault, anyhow::Error> {
+        let mut dir = config_dir();
+        dir.push(id.clone());
+        let mut vault_file = dir.clone();
+        vault_file.push("vault");
+        vault_file.set_extension("turt");
+
+        // read before anything gets made so a bad keyfile doesn't leave an
+        // empty vault behind
+        let (path, hash) = match keyfile {
+            Some(path) => (Some(fs::canonicalize(path)?).filter(|_| remember), Some(keyfile::read(path)?)),
+            None => (None, None),
+        };
+
+        fs::create_dir_all(&dir)?;
+        let lock = Arc::new(lock_dir(&dir)?);
+
+        let mut header = Header { uses_keyfile: hash.is_some(), keyfile: path, ..Header::generate() };
+        let unlock = random_key();
+        header.set_slot("default", kdf, &password, hash.as_deref().map(|k| &k[..]), &unlock)?;
+        let data_key = header.seal(&unlock)?;
+        let key = header.key_for(&data_key)?;
         let data = VaultData::new();
-        let vault = Vault { id, path: data_file, fernet, data };
+        let slot = Some("default".to_string());
+        let vault = Vault { id, path: vault_file, header, key, data_key, unlock: Some(unlock), slot, keyfile: hash, data, _lock: lock };
         vault.write()?;
         Ok(vault)
     }
 
+    // stop keeping the keyfile's location in the header, it has to be given
+    // from then on
+    pub fn forget_keyfile(&mut self) -> Result<(), anyhow::Error> {
+        if self.header.keyfile.take().is_none() {
+            anyhow::bail!("{} doesn't know where a keyfile is", self.id);
+        }
+        self.write()
+    }
+
     pub fn get(&self, entry: &str) -> Option<&HashMap<String, VaultItem>> {
         self.data.get(entry)
     }
 
     pub fn set(&mut self, entry: String, username: String, password: String) -> Result<(), anyhow::Error> {
+        self.set_item(entry, username, VaultItem::Generic(password), HashMap::new())
+    }
+
+    // the password can be either one that was given or one already generated
+    // any other fields go in with it, so the entry is only written the once
+    pub fn set_item(&mut self, entry: String, username: String, password: VaultItem, fields: HashMap<String, VaultItem>) -> Result<(), anyhow::Error> {
         let mut val = HashMap::new();
         val.insert("username".to_string(), VaultItem::Generic(username));
-        val.insert("password".to_string(), VaultItem::Generic(password));
+        val.insert("password".to_string(), password);
+        val.extend(fields);
         self.data.add(entry, val);
         self.write()
     }
 
-    pub fn set_password(&mut self, entry: String, username: String, password: Password) -> Result<(), anyhow::Error> {
-        let mut val = HashMap::new();
-        val.insert("username".to_string(), VaultItem::Generic(username));
-        let password_str = password.generate();
-        val.insert("password".to_string(), VaultItem::GeneratedPassword(password_str, password));
-        self.data.add(entry, val);
+    pub fn set_password(&mut self, entry: String, username: String, password: impl Into<Spec>) -> Result<(), anyhow::Error> {
+        self.set_password_with(entry, username, password, &mut rand::thread_rng())
+    }
+
+    pub fn set_password_with<R: Rng + CryptoRng>(&mut self, entry: String, username: String, password: impl Into<Spec>, rng: &mut R) -> Result<(), anyhow::Error> {
+        self.set_item(entry, username, VaultItem::generated_with(password.into(), rng)?, HashMap::new())
+    }
+
+    // generate a new password from the spec it was made with, the length or
+    // pattern can be changed at the same time
+    // the old password is kept in the history
+    pub fn rotate(&mut self, entry: &str, length: Option<u32>, pattern: Option<String>) -> Result<String, anyhow::Error> {
+        let old = match self.data.get(entry).and_then(|info| info.get("password")) {
+            Some(old) => old.clone(),
+            None => anyhow::bail!("No password for {}", entry),
+        };
+        let spec = match old.spec() {
+            Some(spec) => spec,
+            None => anyhow::bail!("The password for {} wasn't generated, so there is nothing to rotate it with", entry),
+        };
+        let spec = match spec.respec(length, pattern) {
+            Some(spec) => spec,
+            None => anyhow::bail!("Invalid password specification"),
+        };
+        let new = VaultItem::generated(spec)?;
+        let password = new.to_string();
+        self.data.set_field(entry, "password", new);
+        self.write()?;
+        Ok(password)
+    }
+
+    pub fn set_field(&mut self, entry: &str, field: &str, item: VaultItem) -> Result<(), anyhow::Error> {
+        if self.data.get(entry).is_none() {
+            anyhow::bail!("No entry for {}", entry);
+        }
+        self.data.set_field(entry, field, item);
+        self.write()
+    }
+
+    // unlike other fields a note can be the only thing in an entry, so this
+    // creates the entry if needed
+    pub fn set_note(&mut self, entry: &str, field: &str, note: String) -> Result<(), anyhow::Error> {
+        self.data.set_field(entry, field, VaultItem::Note(note));
+        self.write()
+    }
+
+    // same as notes, the entry gets created if needed
+    pub fn set_otp(&mut self, entry: &str, field: &str, otp: Otp) -> Result<(), anyhow::Error> {
+        self.data.set_field(entry, field, VaultItem::Otp(otp));
+        self.write()
+    }
+
+    // the current one time password code and how many seconds it is good for
+    // HOTP codes can only be used once so the counter gets moved along and
+    // saved, without going into the history
+    pub fn otp(&mut self, entry: &str, field: &str) -> Result<(String, Option<u64>), anyhow::Error> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let otp = match self.data.data.get_mut(entry).and_then(|info| info.get_mut(field)) {
+            Some(VaultItem::Otp(otp)) => otp,
+            _ => anyhow::bail!("No one time password for {}", entry),
+        };
+        let code = otp.code(now);
+        let remaining = otp.remaining(now);
+        if let Method::Hotp { .. } = otp.method() {
+            otp.advance();
+            self.write()?;
+        }
+        Ok((code, remaining))
+    }
+
+    pub fn unset_field(&mut self, entry: &str, field: &str) -> Result<(), anyhow::Error> {
+        if self.data.unset_field(entry, field).is_none() {
+            anyhow::bail!("No field {} for {}", field, entry);
+        }
+        self.write()
+    }
+
+    pub fn history(&self, entry: &str) -> Option<&HashMap<String, Vec<HistoryItem>>> {
+        self.data.history(entry)
+    }
+
+    pub fn restore(&mut self, entry: &str, field: &str, version: usize) -> Result<(), anyhow::Error> {
+        if self.data.removed.contains_key(entry) {
+            anyhow::bail!("{} was removed, it can only be restored as a whole", entry);
+        }
+        if self.data.restore(entry, field, version).is_none() {
+            anyhow::bail!("No version {} of {} for {}", version, field, entry);
+        }
         self.write()
     }
 
@@ -140,12 +719,106 @@ impl Vault {
         self.write()
     }
 
+    pub fn restore_entry(&mut self, entry: &str) -> Result<(), anyhow::Error> {
+        if self.data.undelete(entry).is_none() {
+            anyhow::bail!("{} wasn't removed", entry);
+        }
+        self.write()
+    }
+
+    // removes the entry along with its history, so none of its old values
+    // stick around in the vault, the backups get it taken out too
+    // the backups that couldn't be changed still have it, and get handed back
+    pub fn purge(&mut self, entry: &str) -> Result<Vec<u32>, anyhow::Error> {
+        if self.data.purge(entry).is_none() {
+            anyhow::bail!("No entry or history for {}", entry);
+        }
+        self.write()?;
+        let backups = self.backup_numbers()?;
+        Ok(self.move_backups(&self.key, backups, |data| {
+            data.purge(entry);
+        }))
+    }
+
     pub fn entries(&self) -> Vec<String> {
         self.data.entries()
     }
 
+    fn backup_file(&self, number: u32) -> PathBuf {
+        let mut path = self.path.with_file_name("backups");
+        path.push(format!("vault.turt.{}", number));
+        path
+    }
+
+    // the backups there are, newest first
+    fn backup_numbers(&self) -> Result<Vec<u32>, anyhow::Error> {
+        let dir = self.path.with_file_name("backups");
+        let mut numbers = Vec::new();
+        if !dir.exists() {
+            return Ok(numbers);
+        }
+        for entry in fs::read_dir(dir)? {
+            let name = entry?.file_name();
+            if let Some(number) = name.to_str().and_then(|n| n.strip_prefix("vault.turt.")).and_then(|n| n.parse().ok()) {
+                numbers.push(number);
+            }
+        }
+        numbers.sort();
+        Ok(numbers)
+    }
+
+    // keep a copy of the vault that is about to be replaced as backup 1 and
+    // move the rest along, anything past how many are kept gets removed
+    fn backup(&self) -> Result<(), anyhow::Error> {
+        for number in self.backup_numbers()?.into_iter().rev() {
+            if number >= self.header.backups {
+                fs::remove_file(self.backup_file(number))?;
+            } else {
+                fs::rename(self.backup_file(number), self.backup_file(number + 1))?;
+            }
+        }
+        if self.header.backups > 0 && self.path.exists() {
+            write_file(self.backup_file(1), fs::read(&self.path)?)?;
+        }
+        Ok(())
+    }
+
+    pub fn backups(&self) -> Result<Vec<Backup>, anyhow::Error> {
+        let mut backups = Vec::new();
+        for number in self.backup_numbers()? {
+            let path = self.backup_file(number);
+            let saved = fs::metadata(&path)?.modified()?.duration_since(UNIX_EPOCH)?.as_secs();
+            let entries = fs::read(&path).ok()
+                .and_then(|bytes| Container::from_bytes(&bytes).ok())
+                .and_then(|container| read_encrypted(&self.key, &container.ciphertext, container.prefix()).ok())
+                .map(|data| data.entries().len());
+            backups.push(Backup { number, saved, entries });
+        }
+        Ok(backups)
+    }
+
+    // the current vault becomes a backup itself, so this can be undone
+    pub fn restore_backup(&mut self, number: u32) -> Result<(), anyhow::Error> {
+        let path = self.backup_file(number);
+        if !path.exists() {
+            anyhow::bail!("No backup {} for {}", number, self.id);
+        }
+        let container = Container::from_bytes(&fs::read(&path)?)?;
+        self.data = match read_encrypted(&self.key, &container.ciphertext, container.prefix()) {
+            Ok(data) => data,
+            Err(_) => anyhow::bail!("Backup {} can't be opened with the vault's current key", number),
+        };
+        self.write()
+    }
+
+    pub fn set_backups(&mut self, keep: u32) -> Result<(), anyhow::Error> {
+        self.header.backups = keep;
+        self.write()
+    }
+
     pub fn write(&self) -> Result<(), anyhow::Error> {
-        write_encrypted(self.fernet.clone(), self.path.clone(), self.data.clone())
+        self.backup()?;
+        write_encrypted(&self.key, self.path.clone(), &self.header, &self.data)
     }
 }
 
@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::Duration;
+    use turt::agent::Agent;
+
+    fn start(name: &str, timeout: Duration) -> Agent {
+        let path = std::env::temp_dir().join(format!("turt-agent-{}-{}.sock", name, std::process::id()));
+        let server = Agent::at(path.clone());
+        std::thread::spawn(move || server.serve(timeout).unwrap());
+        let agent = Agent::at(path);
+        while !agent.running() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        agent
+    }
+
+    #[test]
+    fn keys() {
+        let agent = start("keys", Duration::from_secs(60));
+        assert_eq!(std::fs::metadata(&agent.path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(agent.get("default").is_none());
+
+        agent.add("default", &[1u8; 32]).unwrap();
+        agent.add("work", &[2u8; 32]).unwrap();
+        assert_eq!(agent.get("default").unwrap().as_slice(), &[1u8; 32]);
+
+        agent.lock(Some("default")).unwrap();
+        assert!(agent.get("default").is_none());
+        assert!(agent.get("work").is_some());
+        agent.lock(None).unwrap();
+        assert!(agent.get("work").is_none());
+
+        agent.stop().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!agent.running());
+        assert!(!agent.path.exists());
+    }
+
+    #[test]
+    fn idle_timeout() {
+        let agent = start("timeout", Duration::from_millis(500));
+        agent.add("default", &[1u8; 32]).unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        // using a key keeps it around
+        assert!(agent.get("default").is_some());
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(agent.get("default").is_some());
+        std::thread::sleep(Duration::from_secs(1));
+        assert!(agent.get("default").is_none());
+        agent.stop().unwrap();
+    }
+}
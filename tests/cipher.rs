@@ -0,0 +1,26 @@
+#[cfg(test)]
+mod tests {
+    use turt::cipher::{Cipher, Key};
+
+    #[test]
+    fn xchacha() {
+        let key = Key::new(Cipher::XChaCha20Poly1305, &[7u8; 32]).unwrap();
+        let encrypted = key.encrypt(b"secret", b"header").unwrap();
+        assert_eq!(key.decrypt(&encrypted, b"header").unwrap(), b"secret");
+
+        // the header is bound to the data, and nonces are never reused
+        assert!(key.decrypt(&encrypted, b"other header").is_err());
+        assert_ne!(key.encrypt(b"secret", b"header").unwrap(), encrypted);
+
+        let other = Key::new(Cipher::XChaCha20Poly1305, &[8u8; 32]).unwrap();
+        assert!(other.decrypt(&encrypted, b"header").is_err());
+        assert!(Key::new(Cipher::XChaCha20Poly1305, &[7u8; 16]).is_none());
+    }
+
+    #[test]
+    fn fernet() {
+        let key = Key::new(Cipher::Fernet, &[7u8; 32]).unwrap();
+        let encrypted = key.encrypt(b"secret", b"header").unwrap();
+        assert_eq!(key.decrypt(&encrypted, b"").unwrap(), b"secret");
+    }
+}
@@ -0,0 +1,31 @@
+#[cfg(test)]
+mod tests {
+    use turt::format::{Container, Header, VERSION};
+
+    fn container() -> Container {
+        let header = Header::generate();
+        let mut container = Container::new(header).unwrap();
+        container.ciphertext = b"ciphertext".to_vec();
+        container
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = container().to_bytes();
+        assert_eq!(&bytes[..4], b"TURT");
+        assert!(bytes.starts_with(container().prefix()));
+        assert_eq!(Container::from_bytes(&bytes).unwrap(), container());
+    }
+
+    #[test]
+    fn rejects() {
+        let bytes = container().to_bytes();
+        assert!(Container::from_bytes(b"gAAAAAB-fernet-token").is_err());
+        assert!(Container::from_bytes(&bytes[..20]).is_err());
+
+        // a vault from a newer version shouldn't be read as if it was this one
+        let mut newer = bytes.clone();
+        newer[4..6].copy_from_slice(&(VERSION + 1).to_be_bytes());
+        assert!(Container::from_bytes(&newer).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use turt::password::{Passphrase, Password, generic};
+
+    const CLASSES: [&str; 5] = ["digit", "upper", "lower", "alpha", "symbol"];
+
+    fn check(class: &str, c: char) -> bool {
+        match class {
+            "digit" => c.is_ascii_digit(),
+            "upper" => c.is_ascii_uppercase(),
+            "lower" => c.is_ascii_lowercase(),
+            "alpha" => c.is_ascii_alphabetic(),
+            "symbol" => c.is_ascii_punctuation(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn seeded_password() {
+        let spec = Password::from_spec(generic(), 24, "digit+symbol".to_string()).unwrap();
+        let a = spec.generate_with(&mut StdRng::seed_from_u64(7));
+        let b = spec.generate_with(&mut StdRng::seed_from_u64(7));
+        let c = spec.generate_with(&mut StdRng::seed_from_u64(8));
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn seeded_passphrase() {
+        let spec = Passphrase::new(5, "-".to_string(), true, true, true).unwrap();
+        let a = spec.generate_with(&mut StdRng::seed_from_u64(7));
+        let b = spec.generate_with(&mut StdRng::seed_from_u64(7));
+        assert_eq!(a, b);
+    }
+
+    proptest! {
+        // every combination of classes, each with its own minimum
+        #[test]
+        fn class_guarantees(mask in 0u32..32, mins in prop::array::uniform5(1u32..4), extra in 0u32..16, seed in any::<u64>()) {
+            let required: Vec<(&str, u32)> = CLASSES.iter().zip(mins).enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, (class, min))| (*class, min))
+                .collect();
+            let pattern = required.iter().map(|(class, min)| format!("{}*{}", class, min)).collect::<Vec<_>>().join("+");
+            let length = required.iter().map(|(_, min)| min).sum::<u32>() + extra;
+
+            let spec = Password::from_spec(generic(), length, pattern).unwrap();
+            let password = spec.generate_with(&mut StdRng::seed_from_u64(seed)).unwrap();
+            prop_assert_eq!(password.chars().count() as u32, length);
+            for (class, min) in &required {
+                prop_assert!(password.chars().filter(|c| check(class, *c)).count() as u32 >= *min);
+            }
+        }
+
+        #[test]
+        fn too_short(mask in 1u32..32, seed in any::<u64>()) {
+            let required: Vec<&str> = CLASSES.iter().enumerate().filter(|(i, _)| mask & (1 << i) != 0).map(|(_, c)| *c).collect();
+            let spec = Password::from_spec(generic(), required.len() as u32 - 1, required.join("+"));
+            prop_assert!(spec.is_none());
+            let spec = Password::from_spec(generic(), required.len() as u32, required.join("+")).unwrap();
+            let password = spec.generate_with(&mut StdRng::seed_from_u64(seed)).unwrap();
+            for class in required {
+                prop_assert!(password.chars().any(|c| check(class, c)));
+            }
+        }
+    }
+}
@@ -0,0 +1,28 @@
+#[cfg(test)]
+mod tests {
+    use turt::member::{parse_public, Identity, Member};
+
+    #[test]
+    fn identity_round_trip() {
+        let path = std::env::temp_dir().join(format!("turt-identity-{}", std::process::id()));
+        let identity = Identity::generate();
+        identity.save(&path).unwrap();
+        assert!(Identity::generate().save(&path).is_err());
+
+        let loaded = Identity::load(&path).unwrap();
+        assert_eq!(loaded.public(), identity.public());
+        assert!(parse_public(&identity.public()).is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn wrap() {
+        let (alice, bob) = (Identity::generate(), Identity::generate());
+        let member = Member::wrap(&alice.public(), b"data key").unwrap();
+        assert_eq!(&alice.unwrap(std::slice::from_ref(&member)).unwrap()[..], b"data key");
+        assert!(bob.unwrap(&[member]).is_err());
+        assert!(Member::wrap("turt-pub-AAAA", b"data key").is_err());
+        // the all zero point would give away the key
+        assert!(Member::wrap(&format!("turt-pub-{}", "A".repeat(43)), b"data key").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use turt::otp::{base32_decode, base32_encode, Algorithm, Method, Otp};
+
+    // test vectors from the appendices of RFC 4226 and RFC 6238
+    #[test]
+    fn hotp_vectors() {
+        let otp = Otp::new(b"12345678901234567890".to_vec(), Algorithm::Sha1, 6, Method::Hotp { counter: 0 }).unwrap();
+        let expected = ["755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583", "399871", "520489"];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(otp.hotp(counter as u64), *code);
+        }
+    }
+
+    #[test]
+    fn totp_vectors() {
+        let sha1 = Otp::new(b"12345678901234567890".to_vec(), Algorithm::Sha1, 8, Method::Totp { period: 30 }).unwrap();
+        let sha256 = Otp::new(b"12345678901234567890123456789012".to_vec(), Algorithm::Sha256, 8, Method::Totp { period: 30 }).unwrap();
+        let sha512 = Otp::new(b"1234567890123456789012345678901234567890123456789012345678901234".to_vec(), Algorithm::Sha512, 8, Method::Totp { period: 30 }).unwrap();
+
+        assert_eq!(sha1.code(59), "94287082");
+        assert_eq!(sha256.code(59), "46119246");
+        assert_eq!(sha512.code(59), "90693936");
+        assert_eq!(sha1.code(1111111109), "07081804");
+        assert_eq!(sha256.code(1111111109), "68084774");
+        assert_eq!(sha512.code(1111111109), "25091201");
+        assert_eq!(sha1.code(20000000000), "65353130");
+        assert_eq!(sha1.remaining(59), Some(1));
+    }
+
+    #[test]
+    fn hotp_advance() {
+        let mut otp = Otp::new(b"12345678901234567890".to_vec(), Algorithm::Sha1, 6, Method::Hotp { counter: 0 }).unwrap();
+        assert_eq!(otp.code(0), "755224");
+        otp.advance();
+        assert_eq!(otp.code(0), "287082");
+        assert_eq!(otp.method(), Method::Hotp { counter: 1 });
+        assert_eq!(otp.remaining(0), None);
+    }
+
+    #[test]
+    fn uri() {
+        let otp = Otp::from_uri("otpauth://totp/Example:alice%40google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example").unwrap();
+        assert_eq!(otp.code(0), Otp::totp("JBSWY3DPEHPK3PXP").unwrap().code(0));
+        assert_eq!(Otp::from_uri(&otp.to_uri()), Some(otp.clone()));
+        assert!(otp.to_uri().contains("issuer=Example"));
+
+        let hotp = Otp::from_uri("otpauth://hotp/Test?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=1&digits=6").unwrap();
+        assert_eq!(hotp.method(), Method::Hotp { counter: 1 });
+        assert_eq!(hotp.code(0), "287082");
+
+        // hotp needs a counter, and secrets have to be base32
+        assert_eq!(Otp::from_uri("otpauth://hotp/Test?secret=GEZDGNBVGY3TQOJQ"), None);
+        assert_eq!(Otp::from_uri("otpauth://totp/Test?secret=not-base32!"), None);
+        assert_eq!(Otp::from_uri("otpauth://totp/Test?secret=JBSWY3DPEHPK3PXP&digits=4"), None);
+    }
+
+    #[test]
+    fn base32() {
+        assert_eq!(base32_decode("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"), Some(b"12345678901234567890".to_vec()));
+        assert_eq!(base32_decode("gezd gnbv gy3t qojq"), Some(b"1234567890".to_vec()));
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode(&base32_encode(b"foobar")), Some(b"foobar".to_vec()));
+        assert_eq!(base32_decode("1"), None);
+    }
+}
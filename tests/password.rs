@@ -0,0 +1,278 @@
+#[cfg(test)]
+mod tests {
+    use turt::password::{Choice, Passphrase, Password, ambiguous, generic, wordlist};
+
+    #[test]
+    fn wordlist_size() {
+        let words: Vec<&str> = include_str!("../src/eff_large_wordlist.txt").lines().collect();
+        assert_eq!(words.len(), 7776);
+        assert!(wordlist().choose().chars().all(|c| c.is_ascii_lowercase() || c == '-'));
+    }
+
+    #[test]
+    fn passphrase_words() {
+        let spec = Passphrase::new(5, " ".to_string(), false, false, false).unwrap();
+        let phrase = spec.generate();
+        assert_eq!(phrase.split(' ').count(), 5);
+    }
+
+    #[test]
+    fn passphrase_extras() {
+        let spec = Passphrase::new(4, ".".to_string(), true, true, true).unwrap();
+        let phrase = spec.generate();
+        let words: Vec<&str> = phrase.split('.').collect();
+        assert_eq!(words.len(), 4);
+        assert!(words.iter().all(|w| w.chars().next().unwrap().is_ascii_uppercase()));
+        assert!(phrase.chars().any(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn passphrase_symbol_not_separator() {
+        let spec = Passphrase::new(4, ".".to_string(), false, false, true).unwrap();
+        for _ in 0..200 {
+            assert_eq!(spec.generate().split('.').count(), 4);
+        }
+        let every: String = turt::password::symbol().iter().collect();
+        assert!(Passphrase::new(4, every, false, false, true).is_none());
+    }
+
+    #[test]
+    fn passphrase_no_words() {
+        assert!(Passphrase::new(0, "-".to_string(), false, false, false).is_none());
+    }
+
+    #[test]
+    fn pattern_counts() {
+        let spec = Password::from_spec(generic(), 12, "digit*3+symbol{2}+upper".to_string()).unwrap();
+        for _ in 0..50 {
+            let p = spec.generate().unwrap();
+            assert_eq!(p.chars().count(), 12);
+            assert!(p.chars().filter(|c| c.is_ascii_digit()).count() >= 3);
+            assert!(p.chars().filter(|c| c.is_ascii_punctuation()).count() >= 2);
+            assert!(p.chars().any(|c| c.is_ascii_uppercase()));
+        }
+    }
+
+    #[test]
+    fn pattern_maximums() {
+        let spec = Password::from_spec(generic(), 32, "digit{1,2}+symbol{,0}".to_string()).unwrap();
+        for _ in 0..50 {
+            let p = spec.generate().unwrap();
+            let digits = p.chars().filter(|c| c.is_ascii_digit()).count();
+            assert!((1..=2).contains(&digits));
+            assert!(!p.chars().any(|c| c.is_ascii_punctuation()));
+        }
+    }
+
+    #[test]
+    fn pattern_invalid() {
+        assert!(Password::from_spec(generic(), 4, "digit*5".to_string()).is_none());
+        assert!(Password::from_spec(generic(), 32, "digit{3,2}".to_string()).is_none());
+        assert!(Password::from_spec(generic(), 32, "digit{a}".to_string()).is_none());
+        assert!(Password::from_spec(generic(), 32, "alpha{,1}+upper+lower".to_string()).is_none());
+    }
+
+    #[test]
+    fn pattern_capped() {
+        // nothing left for the rest of the password once the cap is reached
+        let digits = Choice::new(('0'..='9').collect()).unwrap();
+        assert!(Password::from_spec(digits.clone(), 8, "digit{,0}".to_string()).is_none());
+        assert!(Password::from_spec(digits.clone(), 8, "digit{,2}".to_string()).is_none());
+        assert!(Password::from_spec(digits.clone(), 2, "digit{,2}".to_string()).is_some());
+        assert!(Password::from_spec(generic(), 8, "[0123]*3+digit{,1}".to_string()).is_none());
+        assert!(Password::from_spec(generic(), 8, "upper{,1}+lower{,1}+alpha*3".to_string()).is_none());
+        assert!(Password::from_spec(digits, 8, "alpha*2".to_string()).is_some());
+
+        // sets spanning capped classes only get what the caps leave them
+        assert!(Password::from_spec(generic(), 2, "[aB]*2+upper{,0}+lower{,1}".to_string()).is_none());
+        assert!(Password::from_spec(generic(), 4, "[B]+upper{,0}".to_string()).is_none());
+        let spec = Password::from_spec(generic(), 2, "[aB]*2+upper{,0}+lower{,2}".to_string()).unwrap();
+        for _ in 0..20 {
+            assert_eq!(spec.generate().unwrap(), "aa");
+        }
+
+        let spec = Password::from_spec(generic(), 8, "digit{,0}".to_string()).unwrap();
+        assert!(spec.exclude(&generic().subtract(&Choice::new(('0'..='9').collect()).unwrap()).unwrap()).is_none());
+    }
+
+    #[test]
+    fn legacy_flags() {
+        let spec = Password::from_spec(generic(), 16, "digit+upper".to_string()).unwrap();
+        let mut json = serde_json::to_value(&spec).unwrap();
+        json["digit"] = serde_json::Value::Bool(true);
+        json["upper"] = serde_json::Value::Bool(true);
+        json["lower"] = serde_json::Value::Bool(false);
+        json["alpha"] = serde_json::Value::Bool(false);
+        json["symbol"] = serde_json::Value::Bool(false);
+        let legacy: Password = serde_json::from_value(json).unwrap();
+        assert_eq!(legacy, spec);
+    }
+
+    #[test]
+    fn pattern_sets() {
+        let allowed = Choice::new(('a'..='z').collect()).unwrap();
+        let spec = Password::from_spec(allowed, 10, "[!@#]+oneof(+-)*2+[\\]]".to_string()).unwrap();
+        for _ in 0..50 {
+            let p = spec.generate().unwrap();
+            assert_eq!(p.chars().count(), 10);
+            assert_eq!(p.chars().filter(|c| "!@#".contains(*c)).count(), 1);
+            assert_eq!(p.chars().filter(|c| "+-".contains(*c)).count(), 2);
+            assert_eq!(p.chars().filter(|c| *c == ']').count(), 1);
+        }
+    }
+
+    #[test]
+    fn pattern_sets_invalid() {
+        assert!(Password::from_spec(generic(), 10, "[!@#".to_string()).is_none());
+        assert!(Password::from_spec(generic(), 10, "[]".to_string()).is_none());
+        assert!(Password::from_spec(generic(), 10, "[!@#]{,2}".to_string()).is_none());
+        assert!(Password::from_spec(generic(), 2, "[!@#]*3".to_string()).is_none());
+    }
+
+    #[test]
+    fn choice_subtract() {
+        let digits = Choice::new(('0'..='9').collect()).unwrap();
+        let odd = Choice::new(vec!['1', '3', '5', '7', '9']).unwrap();
+        let even = digits.subtract(&odd).unwrap();
+        assert_eq!(even.iter().collect::<String>(), "02468");
+        assert!(odd.subtract(&digits).is_none());
+    }
+
+    #[test]
+    fn exclude_ambiguous() {
+        let spec = Password::from_spec(generic(), 64, "digit*4+upper*4+lower*4".to_string()).unwrap()
+            .exclude(&ambiguous()).unwrap()
+            .exclude(&Choice::new(vec!['x', 'Y']).unwrap()).unwrap();
+        for _ in 0..50 {
+            let p = spec.generate().unwrap();
+            assert!(!p.chars().any(|c| "0Oo1lI|xY".contains(c)));
+        }
+    }
+
+    #[test]
+    fn exclude_required_class() {
+        let spec = Password::from_spec(generic(), 16, "digit".to_string()).unwrap();
+        let digits = Choice::new(('0'..='9').collect()).unwrap();
+        assert!(spec.exclude(&digits).is_none());
+        let spec = Password::from_spec(generic(), 16, "[0l]".to_string()).unwrap();
+        assert!(spec.exclude(&ambiguous()).is_none());
+    }
+
+    #[test]
+    fn no_runs() {
+        let allowed = Choice::new(vec!['a', 'b', 'c', '1', '2', '3']).unwrap();
+        let spec = Password::from_spec(allowed, 32, "digit".to_string()).unwrap()
+            .max_consecutive(2).unwrap()
+            .no_sequences();
+        for _ in 0..50 {
+            let p: Vec<char> = spec.generate().unwrap().chars().collect();
+            assert_eq!(p.len(), 32);
+            assert!(p.iter().any(|c| c.is_ascii_digit()));
+            for w in p.windows(3) {
+                assert!(!(w[0] == w[1] && w[1] == w[2]));
+                let up = w[1] as i64 - w[0] as i64 == 1 && w[2] as i64 - w[1] as i64 == 1;
+                let down = w[0] as i64 - w[1] as i64 == 1 && w[1] as i64 - w[2] as i64 == 1;
+                assert!(!up && !down, "{:?}", w);
+            }
+        }
+    }
+
+    #[test]
+    fn runs_impossible() {
+        // one character can't be kept out of long runs
+        let only_a = Choice::new(vec!['a']).unwrap();
+        assert!(Password::from_spec(only_a.clone(), 5, "".to_string()).unwrap().max_consecutive(1).is_none());
+        assert!(Password::from_spec(only_a.clone(), 3, "".to_string()).unwrap().max_consecutive(3).is_some());
+
+        // unless something required breaks them up
+        let spec = Password::from_spec(only_a.clone(), 5, "digit*2".to_string()).unwrap().max_consecutive(1).unwrap();
+        for _ in 0..50 {
+            let p: Vec<char> = spec.generate().unwrap().chars().collect();
+            assert!(p.windows(2).all(|w| w[0] != w[1]));
+        }
+        assert!(Password::from_spec(only_a, 6, "digit*2".to_string()).unwrap().max_consecutive(1).is_none());
+
+        // excluding down to one character afterwards counts too
+        let ab = Choice::new(vec!['a', 'b']).unwrap();
+        let spec = Password::from_spec(ab, 5, "".to_string()).unwrap().max_consecutive(1).unwrap();
+        assert!(spec.exclude(&Choice::new(vec!['b']).unwrap()).is_none());
+    }
+
+    #[test]
+    fn no_repeats() {
+        let spec = Password::from_spec(generic(), 40, "upper*5+symbol".to_string()).unwrap()
+            .no_repeats().unwrap();
+        for _ in 0..50 {
+            let mut p: Vec<char> = spec.generate().unwrap().chars().collect();
+            p.sort();
+            p.dedup();
+            assert_eq!(p.len(), 40);
+        }
+        let allowed = Choice::new(('a'..='e').collect()).unwrap();
+        assert!(Password::from_spec(allowed, 6, "".to_string()).unwrap().no_repeats().is_none());
+    }
+
+    #[test]
+    fn no_repeats_impossible() {
+        // only ten digits to go around
+        assert!(Password::from_spec(generic(), 20, "digit*11".to_string()).unwrap().no_repeats().is_none());
+        assert!(Password::from_spec(generic(), 20, "digit*10".to_string()).unwrap().no_repeats().is_some());
+
+        // the digits make the whole thing big enough, but the rest of the
+        // password only has abc to choose from
+        let allowed = Choice::new(vec!['a', 'b', 'c']).unwrap();
+        assert!(Password::from_spec(allowed.clone(), 6, "digit".to_string()).unwrap().no_repeats().is_none());
+        let spec = Password::from_spec(allowed, 6, "digit*3".to_string()).unwrap().no_repeats().unwrap();
+        for _ in 0..50 {
+            let mut p: Vec<char> = spec.generate().unwrap().chars().collect();
+            p.sort();
+            p.dedup();
+            assert_eq!(p.len(), 6);
+        }
+    }
+
+    #[test]
+    fn password_rules() {
+        let rules = "required: upper; required: digit; required: [-_]; allowed: lower; max-consecutive: 2; minlength: 12; maxlength: 20";
+        let spec = Password::from_rules(rules, 32).unwrap();
+        for _ in 0..50 {
+            let p: Vec<char> = spec.generate().unwrap().chars().collect();
+            assert_eq!(p.len(), 20);
+            assert!(p.iter().any(|c| c.is_ascii_uppercase()));
+            assert!(p.iter().any(|c| c.is_ascii_digit()));
+            assert!(p.iter().any(|c| *c == '-' || *c == '_'));
+            assert!(p.iter().all(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_'));
+            assert!(p.windows(3).all(|w| !(w[0] == w[1] && w[1] == w[2])));
+        }
+        assert_eq!(Password::from_rules("minlength: 40", 32).unwrap().generate().unwrap().len(), 40);
+    }
+
+    #[test]
+    fn password_rules_union() {
+        let spec = Password::from_rules("required: digit, [!,]; allowed: lower", 16).unwrap();
+        for _ in 0..50 {
+            let p = spec.generate().unwrap();
+            assert!(p.chars().any(|c| c.is_ascii_digit() || c == '!' || c == ','));
+            assert!(p.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '!' || c == ','));
+        }
+    }
+
+    #[test]
+    fn password_rules_invalid() {
+        assert!(Password::from_rules("required: upper, bogus", 16).is_none());
+        assert!(Password::from_rules("required upper", 16).is_none());
+        assert!(Password::from_rules("minlength: 20; maxlength: 10", 16).is_none());
+        assert!(Password::from_rules("allowed: [abc", 16).is_none());
+    }
+
+    #[test]
+    fn password_rules_unknown() {
+        let spec = Password::from_rules("required: digit; allowed: lower; passwordrules-version: 2; max-lifetime: 90", 16).unwrap();
+        for _ in 0..50 {
+            let p = spec.generate().unwrap();
+            assert_eq!(p.len(), 16);
+            assert!(p.chars().any(|c| c.is_ascii_digit()));
+            assert!(p.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+        }
+    }
+}
@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use turt::secret::Secret;
+    use zeroize::Zeroize;
+
+    // notes down when it gets wiped and what was left
+    struct Tracked {
+        bytes: [u8; 4],
+        wiped: Rc<Cell<Option<[u8; 4]>>>,
+    }
+
+    impl Zeroize for Tracked {
+        fn zeroize(&mut self) {
+            self.bytes.zeroize();
+            self.wiped.set(Some(self.bytes));
+        }
+    }
+
+    impl AsRef<[u8]> for Tracked {
+        fn as_ref(&self) -> &[u8] {
+            &self.bytes
+        }
+    }
+
+    #[test]
+    fn hidden() {
+        let secret = Secret::from("hunter2");
+        assert_eq!(secret.as_str(), "hunter2");
+        assert_eq!(format!("{:?}", secret), "Secret(..)");
+
+        let key: Secret<Vec<u8>> = vec![1u8; 32].into();
+        assert_eq!(key.len(), 32);
+        assert!(!format!("{:?}", key).contains('1'));
+        assert!(Secret::from("").is_empty());
+    }
+
+    #[test]
+    fn wiped_on_drop() {
+        let wiped = Rc::new(Cell::new(None));
+        let secret = Secret::new(Tracked { bytes: *b"key!", wiped: wiped.clone() });
+        assert_eq!(secret.as_ref(), b"key!");
+        assert_eq!(wiped.get(), None);
+        drop(secret);
+        assert_eq!(wiped.get(), Some([0; 4]));
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod tests {
+    use turt::password::{Passphrase, Password, generic};
+    use turt::strength::{estimate, Kind, Rating};
+
+    #[test]
+    fn common_passwords() {
+        assert_eq!(estimate("password").rating(), Rating::VeryWeak);
+        assert_eq!(estimate("P@ssw0rd").rating(), Rating::VeryWeak);
+        assert_eq!(estimate("qwertyuiop").rating(), Rating::VeryWeak);
+        assert_eq!(estimate("abcdef123456").rating(), Rating::VeryWeak);
+    }
+
+    #[test]
+    fn patterns() {
+        let kinds = |p: &str| estimate(p).matches.iter().map(|m| m.kind).collect::<Vec<Kind>>();
+        assert_eq!(kinds("asdfgh"), vec![Kind::Dictionary]);
+        assert_eq!(kinds("zxcvfr"), vec![Kind::Keyboard]);
+        assert_eq!(kinds("aaaaaaaa"), vec![Kind::Repeat]);
+        assert_eq!(kinds("tiger1987"), vec![Kind::Dictionary, Kind::Date]);
+        assert_eq!(kinds("x04/07/1999"), vec![Kind::Bruteforce, Kind::Date]);
+    }
+
+    #[test]
+    fn random_is_stronger() {
+        let weak = estimate("Summer2024!");
+        let strong = estimate("q7#Vz9!kT2@mWx4&");
+        assert!(weak.rating() < Rating::Reasonable);
+        assert!(strong.rating() >= Rating::Strong);
+        assert!(strong.entropy > weak.entropy);
+    }
+
+    #[test]
+    fn spec_entropy() {
+        let spec = Password::from_spec(generic(), 20, "".to_string()).unwrap();
+        assert!((spec.entropy() - 20.0 * 93f64.log2()).abs() < 1e-9);
+        let spec = Password::from_spec(generic(), 20, "digit".to_string()).unwrap();
+        assert!((spec.entropy() - (19.0 * 93f64.log2() + 10f64.log2())).abs() < 1e-9);
+        let phrase = Passphrase::new(6, "-".to_string(), false, false, false).unwrap();
+        assert!((phrase.entropy() - 6.0 * 7776f64.log2()).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,19 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use turt::utils::edit_secret;
+
+    #[test]
+    fn edit_with_editor() {
+        let script = std::env::temp_dir().join(format!("turt-editor-{}.sh", std::process::id()));
+        fs::write(&script, "#!/bin/sh\nls -ld \"$(dirname \"$1\")\" | cut -c1-10 >> \"$1\"\necho done >> \"$1\"\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o700)).unwrap();
+        std::env::remove_var("VISUAL");
+        std::env::set_var("EDITOR", &script);
+
+        let note = edit_secret("recovery codes\n", true).unwrap();
+        assert_eq!(note, "recovery codes\ndrwx------\ndone\n");
+        fs::remove_file(script).unwrap();
+    }
+}
@@ -0,0 +1,554 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Once;
+    use base64::{engine::general_purpose, Engine};
+    use fernet::Fernet;
+    use turt::cipher::Cipher;
+    use turt::format::Container;
+    use turt::kdf::Kdf;
+    use turt::keyfile;
+    use turt::member::Identity;
+    use turt::otp::{Algorithm, Method, Otp};
+    use turt::password::{Password, generic};
+    use turt::vault::{is_secret, Vault, VaultItem};
+
+    static HOME: Once = Once::new();
+
+    // keep the vaults out of the real home directory, every test uses its own
+    // vault name so they can share it
+    fn setup(id: &str) -> Vault {
+        HOME.call_once(|| {
+            let dir = std::env::temp_dir().join(format!("turt-test-{}", std::process::id()));
+            std::env::set_var("HOME", dir);
+        });
+        Vault::create(id.to_string(), "hunter2".into()).unwrap()
+    }
+
+    #[test]
+    fn reopen() {
+        let mut vault = setup("reopen");
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        drop(vault);
+        let vault = Vault::new("reopen".to_string(), "hunter2".into()).unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+        drop(vault);
+        assert!(Vault::new("reopen".to_string(), "wrong".into()).is_err());
+    }
+
+    #[test]
+    fn rotate() {
+        let mut vault = setup("rotate");
+        let spec = Password::from_spec(generic(), 20, "digit".to_string()).unwrap();
+        vault.set_password("site".to_string(), "me".to_string(), spec).unwrap();
+        let old = vault.get("site").unwrap()["password"].to_string();
+
+        let new = vault.rotate("site", None, None).unwrap();
+        assert_ne!(old, new);
+        assert_eq!(new.len(), 20);
+
+        let new = vault.rotate("site", Some(12), Some("upper*3".to_string())).unwrap();
+        assert_eq!(new.len(), 12);
+        assert!(new.chars().filter(|c| c.is_ascii_uppercase()).count() >= 3);
+        drop(vault);
+
+        let vault = Vault::new("rotate".to_string(), "hunter2".into()).unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), new);
+    }
+
+    #[test]
+    fn rotate_manual() {
+        let mut vault = setup("rotate_manual");
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        assert!(vault.rotate("site", None, None).is_err());
+        assert!(vault.rotate("missing", None, None).is_err());
+    }
+
+    #[test]
+    fn history_restore() {
+        let mut vault = setup("history_restore");
+        vault.set("site".to_string(), "me".to_string(), "first".to_string()).unwrap();
+        vault.set("site".to_string(), "me".to_string(), "second".to_string()).unwrap();
+        vault.set("site".to_string(), "me".to_string(), "third".to_string()).unwrap();
+
+        let history = vault.history("site").unwrap();
+        assert!(!history.contains_key("username"));
+        let old: Vec<String> = history["password"].iter().map(|h| h.item.to_string()).collect();
+        assert_eq!(old, vec!["first", "second"]);
+
+        vault.restore("site", "password", 2).unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "first");
+        let old: Vec<String> = vault.history("site").unwrap()["password"].iter().map(|h| h.item.to_string()).collect();
+        assert_eq!(old, vec!["second", "third"]);
+        assert!(vault.restore("site", "password", 3).is_err());
+    }
+
+    #[test]
+    fn history_after_remove() {
+        let mut vault = setup("history_after_remove");
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        vault.remove("site").unwrap();
+        assert!(vault.get("site").is_none());
+        drop(vault);
+
+        let mut vault = Vault::new("history_after_remove".to_string(), "hunter2".into()).unwrap();
+        // only the whole entry can come back
+        assert!(vault.restore("site", "password", 1).is_err());
+        assert!(vault.get("site").is_none());
+        vault.restore_entry("site").unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+        assert_eq!(vault.get("site").unwrap()["username"].to_string(), "me");
+        assert!(vault.restore_entry("site").is_err());
+    }
+
+    #[test]
+    fn purge() {
+        let mut vault = setup("purge");
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        vault.set("site".to_string(), "me".to_string(), "secret2".to_string()).unwrap();
+        assert!(vault.purge("site").unwrap().is_empty());
+        assert!(vault.get("site").is_none());
+        assert!(vault.history("site").is_none());
+
+        // the backups from before don't bring it back
+        vault.restore_backup(1).unwrap();
+        assert!(vault.get("site").is_none());
+        assert!(vault.history("site").is_none());
+        vault.restore_backup(3).unwrap();
+        assert!(vault.get("site").is_none());
+
+        // already removed entries still have history to get rid of
+        vault.set("other".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        vault.remove("other").unwrap();
+        vault.purge("other").unwrap();
+        assert!(vault.history("other").is_none());
+        assert!(vault.restore_entry("other").is_err());
+        assert!(vault.purge("other").is_err());
+    }
+
+    #[test]
+    fn custom_fields() {
+        let mut vault = setup("custom_fields");
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        vault.set_field("site", "email", VaultItem::Generic("me@example.com".to_string())).unwrap();
+        vault.set_field("site", "pin", VaultItem::Secret("1234".to_string())).unwrap();
+        assert!(vault.set_field("missing", "pin", VaultItem::Secret("1234".to_string())).is_err());
+        drop(vault);
+
+        let vault = Vault::new("custom_fields".to_string(), "hunter2".into()).unwrap();
+        let info = vault.get("site").unwrap();
+        assert!(!is_secret("email", &info["email"]));
+        assert!(is_secret("pin", &info["pin"]));
+        assert!(is_secret("password", &info["password"]));
+        assert!(!is_secret("username", &info["username"]));
+
+        let mut vault = vault;
+        vault.unset_field("site", "pin").unwrap();
+        assert!(!vault.get("site").unwrap().contains_key("pin"));
+        assert!(vault.unset_field("site", "pin").is_err());
+        assert_eq!(vault.history("site").unwrap()["pin"][0].item.to_string(), "1234");
+    }
+
+    #[test]
+    fn set_with_fields() {
+        let mut vault = setup("set_with_fields");
+        let before = vault.backups().unwrap().len();
+        let fields = [
+            ("email".to_string(), VaultItem::Generic("me@example.com".to_string())),
+            ("pin".to_string(), VaultItem::Secret("1234".to_string())),
+        ].into_iter().collect();
+        vault.set_item("site".to_string(), "me".to_string(), VaultItem::Generic("secret".to_string()), fields).unwrap();
+        // everything went in with a single write
+        assert_eq!(vault.backups().unwrap().len(), before + 1);
+        let info = vault.get("site").unwrap();
+        assert_eq!(info["email"].to_string(), "me@example.com");
+        assert_eq!(info["pin"].to_string(), "1234");
+        assert_eq!(info["password"].to_string(), "secret");
+    }
+
+    #[test]
+    fn notes() {
+        let mut vault = setup("notes");
+        vault.set_note("codes", "note", "1111\n2222\n".to_string()).unwrap();
+        let info = vault.get("codes").unwrap();
+        assert_eq!(info["note"], VaultItem::Note("1111\n2222\n".to_string()));
+        assert!(is_secret("note", &info["note"]));
+    }
+
+    #[test]
+    fn change_password() {
+        let mut vault = setup("passwd");
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        vault.change_password("hunter3".into()).unwrap();
+        drop(vault);
+        assert!(Vault::new("passwd".to_string(), "hunter2".into()).is_err());
+        let vault = Vault::new("passwd".to_string(), "hunter3".into()).unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+    }
+
+    #[test]
+    fn change_password_interrupted() {
+        let mut vault = setup("passwd-crash");
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        let path = vault.path.clone();
+        let tmp = path.with_file_name("vault.turt.tmp");
+        let old = std::fs::read(&path).unwrap();
+
+        // stopped partway through writing the new file, before it replaced
+        // the old one
+        vault.change_password("hunter3".into()).unwrap();
+        let new = std::fs::read(&path).unwrap();
+        std::fs::write(&tmp, &new[..new.len() / 2]).unwrap();
+        std::fs::write(&path, &old).unwrap();
+        drop(vault);
+        assert!(Vault::new("passwd-crash".to_string(), "hunter3".into()).is_err());
+        let mut vault = Vault::new("passwd-crash".to_string(), "hunter2".into()).unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+
+        // a write that fails keeps the old password working
+        std::fs::remove_file(&tmp).unwrap();
+        std::fs::create_dir(&tmp).unwrap();
+        assert!(vault.change_password("hunter3".into()).is_err());
+        drop(vault);
+        std::fs::remove_dir(&tmp).unwrap();
+        assert!(Vault::new("passwd-crash".to_string(), "hunter3".into()).is_err());
+        let vault = Vault::new("passwd-crash".to_string(), "hunter2".into()).unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+    }
+
+    #[test]
+    fn upgrade_kdf() {
+        let mut vault = setup("kdf");
+        assert_eq!(vault.slot().unwrap().kdf, Kdf::legacy());
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        let kdf = Kdf::new("argon2id", 12).unwrap();
+        vault.upgrade_kdf("hunter2".into(), kdf).unwrap();
+        drop(vault);
+        let vault = Vault::new("kdf".to_string(), "hunter2".into()).unwrap();
+        assert_eq!(vault.slot().unwrap().kdf, kdf);
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+        // what a new slot falls back on
+        assert_eq!(Kdf::new(kdf.name(), kdf.cost()), Some(kdf));
+        assert_eq!(Kdf::legacy().cost(), 16);
+    }
+
+    // how the key was made back then, the derived bytes as a fernet key
+    fn create_fernet(password: &str, salt: &str, kdf: &Kdf) -> Option<Fernet> {
+        let out = kdf.derive(password.as_bytes(), salt)?;
+        Fernet::new(&general_purpose::URL_SAFE.encode(&*out))
+    }
+
+    // the layout from before the single file, a fernet token in data.json
+    fn legacy(id: &str, salt: &str, kdf: &Kdf) -> std::path::PathBuf {
+        let dir = setup(id).path.parent().unwrap().to_path_buf();
+        std::fs::remove_file(dir.join("vault.turt")).unwrap();
+        let fernet = create_fernet("hunter2", salt, kdf).unwrap();
+        let data = r#"{"data":{"site":{"password":{"Generic":"secret"}}}}"#;
+        std::fs::write(dir.join("data.json"), fernet.encrypt(data.as_bytes())).unwrap();
+        dir
+    }
+
+    #[test]
+    fn migrate_salt() {
+        let dir = legacy("legacy", "somesalt", &Kdf::legacy());
+        std::fs::write(dir.join("salt.txt"), "somesalt").unwrap();
+        assert!(Vault::new("legacy".to_string(), "wrong".into()).is_err());
+        assert!(dir.join("data.json").exists());
+
+        let vault = Vault::new("legacy".to_string(), "hunter2".into()).unwrap();
+        assert_eq!(vault.slot().unwrap().kdf, Kdf::legacy());
+        assert_eq!(vault.header.cipher, Cipher::Fernet);
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+        assert!(!dir.join("data.json").exists());
+        assert!(!dir.join("salt.txt").exists());
+        drop(vault);
+        let mut vault = Vault::new("legacy".to_string(), "hunter2".into()).unwrap();
+
+        // re-keying moves it off of fernet
+        vault.change_password("hunter3".into()).unwrap();
+        drop(vault);
+        let vault = Vault::new("legacy".to_string(), "hunter3".into()).unwrap();
+        assert_eq!(vault.header.cipher, Cipher::XChaCha20Poly1305);
+    }
+
+    #[test]
+    fn header_tampering() {
+        let vault = setup("tamper");
+        assert_eq!(vault.header.cipher, Cipher::XChaCha20Poly1305);
+        let container = Container::from_bytes(&std::fs::read(&vault.path).unwrap()).unwrap();
+
+        // a header that means the same thing but isn't byte for byte the same
+        let header = format!(" {}", serde_json::to_string(&container.header).unwrap());
+        let mut bytes = container.prefix()[..6].to_vec();
+        bytes.extend_from_slice(&(header.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(&container.ciphertext);
+        assert_eq!(Container::from_bytes(&bytes).unwrap().header, container.header);
+
+        std::fs::write(&vault.path, &bytes).unwrap();
+        drop(vault);
+        assert!(Vault::new("tamper".to_string(), "hunter2".into()).is_err());
+    }
+
+    #[test]
+    fn hotp_counter_saved() {
+        let mut vault = setup("hotp");
+        let otp = Otp::new(b"12345678901234567890".to_vec(), Algorithm::Sha1, 6, Method::Hotp { counter: 0 }).unwrap();
+        vault.set_otp("site", "otp", otp).unwrap();
+        assert_eq!(vault.otp("site", "otp").unwrap(), ("755224".to_string(), None));
+        drop(vault);
+
+        let mut vault = Vault::new("hotp".to_string(), "hunter2".into()).unwrap();
+        assert_eq!(vault.otp("site", "otp").unwrap(), ("287082".to_string(), None));
+        assert!(vault.history("site").is_none());
+        assert!(vault.otp("site", "password").is_err());
+    }
+
+    #[test]
+    fn locked() {
+        // a second turt waits for the first to finish instead of losing its write
+        let mut vault = setup("locked");
+        let other = std::thread::spawn(|| {
+            let mut vault = Vault::new("locked".to_string(), "hunter2".into()).unwrap();
+            vault.set("other".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        });
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        drop(vault);
+        other.join().unwrap();
+
+        let vault = Vault::new("locked".to_string(), "hunter2".into()).unwrap();
+        assert!(vault.get("site").is_some());
+        assert!(vault.get("other").is_some());
+    }
+
+    #[test]
+    fn backups() {
+        let mut vault = setup("backups");
+        vault.set("first".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        vault.set("second".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        let entries: Vec<Option<usize>> = vault.backups().unwrap().iter().map(|b| b.entries).collect();
+        assert_eq!(entries, vec![Some(1), Some(0)]);
+
+        vault.remove("first").unwrap();
+        vault.restore_backup(1).unwrap();
+        assert!(vault.get("first").is_some());
+        assert!(vault.restore_backup(10).is_err());
+
+        vault.set_backups(2).unwrap();
+        vault.set("third".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        assert_eq!(vault.backups().unwrap().len(), 2);
+
+        // still usable after the key changes
+        vault.change_password("hunter3".into()).unwrap();
+        assert!(vault.backups().unwrap().iter().all(|b| b.entries.is_some()));
+        vault.restore_backup(2).unwrap();
+        assert!(vault.get("third").is_none());
+    }
+
+    #[test]
+    fn backups_unopenable() {
+        let mut vault = setup("backups-unopenable");
+        vault.set("first".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        vault.set("second".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+
+        // made by some other vault, the key doesn't open it
+        let other = setup("backups-other");
+        let foreign = std::fs::read(&other.path).unwrap();
+        let backup = vault.path.with_file_name("backups").join("vault.turt.2");
+        std::fs::write(&backup, &foreign).unwrap();
+
+        assert_eq!(vault.change_password("hunter3".into()).unwrap(), vec![2]);
+        assert_eq!(std::fs::read(&backup).unwrap(), foreign);
+        let entries: Vec<Option<usize>> = vault.backups().unwrap().iter().map(|b| b.entries).collect();
+        assert_eq!(entries, vec![Some(1), None]);
+
+        // one that can't even be read doesn't undo the change that already
+        // happened to the vault
+        let unreadable = vault.path.with_file_name("backups").join("vault.turt.5");
+        std::fs::create_dir(&unreadable).unwrap();
+        let mut skipped = vault.change_password("hunter4".into()).unwrap();
+        skipped.sort();
+        assert_eq!(skipped, vec![2, 5]);
+        std::fs::remove_dir(&unreadable).unwrap();
+        drop(vault);
+        assert!(Vault::new("backups-unopenable".to_string(), "hunter4".into()).is_ok());
+    }
+
+    #[test]
+    fn open_with_key() {
+        let mut vault = setup("with_key");
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        drop(vault);
+
+        let key = Vault::derive_key("with_key".to_string(), "hunter2".into(), None).unwrap();
+        let vault = Vault::with_key("with_key".to_string(), &key).unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+        drop(vault);
+
+        assert!(Vault::derive_key("with_key".to_string(), "wrong".into(), None).is_err());
+        assert!(Vault::with_key("with_key".to_string(), &[0u8; 32]).is_err());
+        assert!(Vault::with_key("missing".to_string(), &key).is_err());
+    }
+
+    #[test]
+    fn keyfile() {
+        setup("keyfile_home");
+        let dir = std::env::temp_dir().join(format!("turt-keyfile-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path, other) = (dir.join("key"), dir.join("other"));
+        keyfile::generate(&path).unwrap();
+        keyfile::generate(&other).unwrap();
+        assert!(keyfile::generate(&path).is_err());
+
+        let mut vault = Vault::create_with("keyfile".to_string(), "hunter2".into(), Kdf::legacy(), Some(&path), true).unwrap();
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        vault.change_password("hunter3".into()).unwrap();
+        drop(vault);
+
+        // found where it was made, or given somewhere else
+        let vault = Vault::new("keyfile".to_string(), "hunter3".into()).unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+        drop(vault);
+        let moved = dir.join("moved");
+        std::fs::rename(&path, &moved).unwrap();
+        assert!(Vault::new("keyfile".to_string(), "hunter3".into()).is_err());
+        assert!(Vault::open("keyfile".to_string(), "hunter3".into(), Some(&moved)).is_ok());
+
+        // both are needed
+        assert!(Vault::open("keyfile".to_string(), "hunter3".into(), Some(&other)).is_err());
+        assert!(Vault::open("keyfile".to_string(), "wrong".into(), Some(&moved)).is_err());
+        let key = Vault::derive_key("keyfile".to_string(), "hunter3".into(), Some(&moved)).unwrap();
+        assert!(Vault::with_key("keyfile".to_string(), &key).is_ok());
+
+        // a vault without one doesn't take one
+        assert!(Vault::open("keyfile_home".to_string(), "hunter2".into(), Some(&moved)).is_err());
+
+        // once forgotten it always has to be given
+        let mut vault = Vault::open("keyfile".to_string(), "hunter3".into(), Some(&moved)).unwrap();
+        vault.forget_keyfile().unwrap();
+        assert!(vault.forget_keyfile().is_err());
+        drop(vault);
+        std::fs::rename(&moved, &path).unwrap();
+        assert!(Vault::new("keyfile".to_string(), "hunter3".into()).is_err());
+        assert!(Vault::open("keyfile".to_string(), "hunter3".into(), Some(&path)).is_ok());
+    }
+
+    #[test]
+    fn keyfile_not_remembered() {
+        setup("keyfile_forgotten_home");
+        let dir = std::env::temp_dir().join(format!("turt-keyfile-forgotten-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("key");
+        keyfile::generate(&path).unwrap();
+
+        let vault = Vault::create_with("keyfile_forgotten".to_string(), "hunter2".into(), Kdf::legacy(), Some(&path), false).unwrap();
+        assert!(vault.header.uses_keyfile);
+        assert!(vault.header.keyfile.is_none());
+        let bytes = std::fs::read(&vault.path).unwrap();
+        let name = dir.file_name().unwrap().to_str().unwrap();
+        assert!(!bytes.windows(name.len()).any(|w| w == name.as_bytes()));
+        drop(vault);
+
+        assert!(Vault::new("keyfile_forgotten".to_string(), "hunter2".into()).is_err());
+        assert!(Vault::open("keyfile_forgotten".to_string(), "hunter2".into(), Some(&path)).is_ok());
+    }
+
+    #[test]
+    fn members() {
+        let mut vault = setup("members");
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        let (alice, bob) = (Identity::generate(), Identity::generate());
+        vault.add_member(&alice.public()).unwrap();
+        vault.add_member(&bob.public()).unwrap();
+        assert!(vault.add_member(&bob.public()).is_err());
+        assert!(vault.add_member("not a key").is_err());
+        drop(vault);
+
+        let vault = Vault::with_identity("members".to_string(), &alice).unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+        drop(vault);
+
+        // a member can't change the key without the password
+        let mut vault = Vault::with_identity("members".to_string(), &bob).unwrap();
+        assert!(vault.remove_member(&alice.public()).is_err());
+        drop(vault);
+
+        let mut vault = Vault::new("members".to_string(), "hunter2".into()).unwrap();
+        vault.remove_member(&alice.public()).unwrap();
+        assert!(vault.remove_member(&alice.public()).is_err());
+        drop(vault);
+        assert!(Vault::with_identity("members".to_string(), &alice).is_err());
+        assert!(Vault::with_identity("members".to_string(), &bob).is_ok());
+        assert!(Vault::new("members".to_string(), "hunter2".into()).is_ok());
+    }
+
+    #[test]
+    fn members_of_legacy() {
+        let dir = legacy("legacy_members", "somesalt", &Kdf::legacy());
+        std::fs::write(dir.join("salt.txt"), "somesalt").unwrap();
+        let mut vault = Vault::new("legacy_members".to_string(), "hunter2".into()).unwrap();
+        assert!(vault.header.wrapped.is_none());
+
+        // the member gets a data key, not the key from the password
+        let alice = Identity::generate();
+        vault.add_member(&alice.public()).unwrap();
+        assert!(vault.header.wrapped.is_some());
+        assert_eq!(vault.header.cipher, Cipher::XChaCha20Poly1305);
+        drop(vault);
+        let vault = Vault::with_identity("legacy_members".to_string(), &alice).unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+        drop(vault);
+        assert!(Vault::new("legacy_members".to_string(), "hunter2".into()).is_ok());
+    }
+
+    #[test]
+    fn slots() {
+        let mut vault = setup("slots");
+        vault.set("site".to_string(), "me".to_string(), "secret".to_string()).unwrap();
+        vault.add_slot("break-glass", "emergency".into(), Kdf::new("scrypt", 10).unwrap()).unwrap();
+        assert!(vault.add_slot("break-glass", "again".into(), Kdf::legacy()).is_err());
+        let names: Vec<&str> = vault.header.slots.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["default", "break-glass"]);
+        drop(vault);
+
+        // either password opens it, and changing one leaves the data key alone
+        let mut vault = Vault::new("slots".to_string(), "emergency".into()).unwrap();
+        assert_eq!(vault.slot().unwrap().name, "break-glass");
+        let wrapped = vault.header.wrapped.clone();
+        vault.change_password("emergency2".into()).unwrap();
+        assert_eq!(vault.header.wrapped, wrapped);
+        drop(vault);
+        assert!(Vault::new("slots".to_string(), "emergency".into()).is_err());
+        let mut vault = Vault::new("slots".to_string(), "hunter2".into()).unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+
+        // a removed password doesn't open the vault or its backups
+        vault.remove_slot("break-glass").unwrap();
+        assert!(vault.remove_slot("break-glass").is_err());
+        assert!(vault.remove_slot("default").is_err());
+        let backup = vault.path.with_file_name("backups").join("vault.turt.1");
+        let container = Container::from_bytes(&std::fs::read(backup).unwrap()).unwrap();
+        assert!(container.header.unlock(&"emergency2".into(), None).is_err());
+        drop(vault);
+        assert!(Vault::new("slots".to_string(), "emergency2".into()).is_err());
+        assert!(Vault::new("slots".to_string(), "hunter2".into()).is_ok());
+    }
+
+    #[test]
+    fn slots_of_legacy() {
+        let dir = legacy("legacy_slots", "somesalt", &Kdf::legacy());
+        std::fs::write(dir.join("salt.txt"), "somesalt").unwrap();
+        let mut vault = Vault::new("legacy_slots".to_string(), "hunter2".into()).unwrap();
+        assert!(!vault.header.slots[0].is_wrapped());
+
+        // the old password keeps working but through a new vault key
+        vault.add_slot("second", "other".into(), Kdf::legacy()).unwrap();
+        assert!(vault.header.slots.iter().all(|s| s.is_wrapped()));
+        drop(vault);
+        let vault = Vault::new("legacy_slots".to_string(), "hunter2".into()).unwrap();
+        assert_eq!(vault.get("site").unwrap()["password"].to_string(), "secret");
+        drop(vault);
+        assert!(Vault::new("legacy_slots".to_string(), "other".into()).is_ok());
+        assert!(Vault::new("legacy_slots".to_string(), "wrong".into()).is_err());
+    }
+}